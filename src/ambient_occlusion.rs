@@ -0,0 +1,64 @@
+use crate::color::Color;
+use crate::integrator::Integrator;
+use crate::ray::Ray;
+use crate::ray_intersect::Intersect;
+use crate::sampling::{cosine_hemisphere, Rng};
+use crate::scene::Scene;
+
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub samples: u32,
+    pub radius: f32,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: u32, radius: f32) -> Self {
+        AmbientOcclusion { samples, radius }
+    }
+
+    // Fracción de rayos del hemisferio que no chocan con nada dentro del radio (1 = sin oclusión)
+    pub fn visibility(&self, scene: &Scene, intersect: &Intersect, rng: &mut Rng) -> f32 {
+        if self.samples == 0 {
+            return 1.0;
+        }
+
+        let mut visible = 0;
+        for _ in 0..self.samples {
+            let direction = cosine_hemisphere(&intersect.normal, rng);
+            if !scene.is_occluded(intersect, &direction, self.radius) {
+                visible += 1;
+            }
+        }
+
+        visible as f32 / self.samples as f32
+    }
+}
+
+pub struct AmbientOcclusionIntegrator {
+    pub settings: AmbientOcclusion,
+}
+
+impl AmbientOcclusionIntegrator {
+    pub fn new(settings: AmbientOcclusion) -> Self {
+        AmbientOcclusionIntegrator { settings }
+    }
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn name(&self) -> &str {
+        "Ambient occlusion"
+    }
+
+    fn is_progressive(&self) -> bool {
+        true
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, rng: &mut Rng) -> Color {
+        let Some(intersect) = scene.intersect(ray) else {
+            return Color::new(1.0, 1.0, 1.0);
+        };
+
+        let ao = self.settings.visibility(scene, &intersect, rng);
+        Color::new(ao, ao, ao)
+    }
+}
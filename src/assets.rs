@@ -0,0 +1,64 @@
+use image::ImageError;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::texture::{ImageTexture, Texture};
+
+// Caché de texturas por ruta: cada archivo se carga una sola vez y los materiales comparten el Rc
+pub struct AssetManager {
+    textures: HashMap<String, Rc<ImageTexture>>,
+}
+
+impl AssetManager {
+    pub fn new() -> Self {
+        AssetManager {
+            textures: HashMap::new(),
+        }
+    }
+
+    // Devuelve la textura ya cargada o la carga la primera vez
+    pub fn texture(&mut self, path: &str) -> Rc<dyn Texture> {
+        let texture = self
+            .textures
+            .entry(path.to_string())
+            .or_insert_with(|| Rc::new(ImageTexture::new(path)));
+        texture.clone()
+    }
+
+    pub fn textures(&self) -> Vec<Rc<dyn Texture>> {
+        self.textures.values().map(|texture| texture.clone() as Rc<dyn Texture>).collect()
+    }
+
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    // Memoria total de los texeles cargados
+    pub fn memory_bytes(&self) -> usize {
+        self.textures.values().map(|texture| texture.memory_bytes()).sum()
+    }
+
+    // Recarga solo las texturas cuyo archivo cambió; devuelve la ruta y el resultado de cada una
+    pub fn reload_changed(&self) -> Vec<(String, Result<(), ImageError>)> {
+        self.textures
+            .values()
+            .filter(|texture| texture.is_modified())
+            .map(|texture| (texture.path().to_string(), texture.reload()))
+            .collect()
+    }
+
+    // Recarga todas las texturas en su lugar y devuelve las que fallaron;
+    // los materiales que las usan ven la imagen nueva
+    pub fn reload_all(&self) -> Vec<(String, ImageError)> {
+        self.textures
+            .values()
+            .filter_map(|texture| texture.reload().err().map(|e| (texture.path().to_string(), e)))
+            .collect()
+    }
+}
+
+impl Default for AssetManager {
+    fn default() -> Self {
+        AssetManager::new()
+    }
+}
@@ -0,0 +1,72 @@
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+use crate::ray::Ray;
+use crate::ray_intersect::count_intersection_test;
+
+// Caja alineada a los ejes que envuelve un objeto
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.inf(&other.min), self.max.sup(&other.max))
+    }
+
+    // Caja que envuelve las 8 esquinas transformadas
+    pub fn transform(&self, matrix: &Mat4) -> Aabb {
+        let mut min = Vec3::repeat(f32::INFINITY);
+        let mut max = Vec3::repeat(f32::NEG_INFINITY);
+        for corner in 0..8 {
+            let x = if corner & 1 == 0 { self.min.x } else { self.max.x };
+            let y = if corner & 2 == 0 { self.min.y } else { self.max.y };
+            let z = if corner & 4 == 0 { self.min.z } else { self.max.z };
+            let point = (matrix * Vec4::new(x, y, z, 1.0)).xyz();
+            min = min.inf(&point);
+            max = max.sup(&point);
+        }
+        Aabb::new(min, max)
+    }
+
+    pub fn hit(&self, ray: &Ray) -> bool {
+        self.ray_range(ray).is_some()
+    }
+
+    // Tramo del rayo dentro de la caja, recortado a [tmin, tmax] del rayo
+    pub fn ray_range(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let (tmin, tmax) = self.slab(ray)?;
+        let tmin = tmin.max(ray.tmin);
+        let tmax = tmax.min(ray.tmax);
+        if tmin <= tmax { Some((tmin, tmax)) } else { None }
+    }
+
+    // Parámetros de entrada y salida del rayo, aunque queden detrás del origen; las caras cuentan como parte de la caja.
+    // Un eje donde la dirección es cero no se divide (daría 0 * infinito = NaN en el borde):
+    // el rayo es paralelo a esas caras y solo sirve si su origen está entre ellas
+    pub fn slab(&self, ray: &Ray) -> Option<(f32, f32)> {
+        count_intersection_test();
+        let mut tmin = f32::NEG_INFINITY;
+        let mut tmax = f32::INFINITY;
+        for axis in 0..3 {
+            let origin = ray.origin[axis];
+            if ray.direction[axis] == 0.0 {
+                if origin < self.min[axis] || origin > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = (self.min[axis] - origin) * ray.inverse_direction[axis];
+            let t1 = (self.max[axis] - origin) * ray.inverse_direction[axis];
+            tmin = tmin.max(t0.min(t1));
+            tmax = tmax.min(t0.max(t1));
+        }
+        if tmin <= tmax { Some((tmin, tmax)) } else { None }
+    }
+}
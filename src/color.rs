@@ -1,5 +1,5 @@
 use std::fmt;
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Sub};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Color {
@@ -25,13 +25,37 @@ impl Color {
         Color { r: 0.0, g: 0.0, b: 0.0 }
     }
 
-    pub fn to_u32(&self) -> u32 {
+    pub fn to_u32(self) -> u32 {
         let r = (self.r.clamp(0.0, 1.0) * 255.0) as u32;
         let g = (self.g.clamp(0.0, 1.0) * 255.0) as u32;
         let b = (self.b.clamp(0.0, 1.0) * 255.0) as u32;
         (r << 16) | (g << 8) | b
     }
 
+    // Curva sRGB: de valores codificados como en las imágenes a luz lineal
+    pub fn srgb_to_linear(self) -> Self {
+        let decode = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Color::new(decode(self.r), decode(self.g), decode(self.b))
+    }
+
+    pub fn linear_to_srgb(self) -> Self {
+        let encode = |c: f32| {
+            let c = c.max(0.0);
+            if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        Color::new(encode(self.r), encode(self.g), encode(self.b))
+    }
+
     pub fn clamp(self) -> Self {
         Color {
             r: self.r.clamp(0.0, 1.0),
@@ -53,6 +77,18 @@ impl Add for Color {
     }
 }
 
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color {
+            r: self.r - other.r,
+            g: self.g - other.g,
+            b: self.b - other.b,
+        }
+    }
+}
+
 impl Mul<f32> for Color {
     type Output = Color;
 
@@ -0,0 +1,262 @@
+use crate::bounds::Aabb;
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, Interval, RayIntersect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,
+    #[allow(dead_code)]  // La escena de ejemplo solo usa unión y diferencia
+    Intersection,
+    // Lo que está en el primer objeto y no en el segundo
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+// Combinación booleana de dos sólidos a partir de sus tramos sobre el rayo.
+// En una diferencia las caras talladas usan el material del segundo objeto
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Box<dyn RayIntersect>,
+    pub right: Box<dyn RayIntersect>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Box<dyn RayIntersect>, right: Box<dyn RayIntersect>) -> Self {
+        Csg { operation, left, right }
+    }
+
+    pub fn union(left: Box<dyn RayIntersect>, right: Box<dyn RayIntersect>) -> Self {
+        Csg::new(CsgOperation::Union, left, right)
+    }
+
+    #[allow(dead_code)]
+    pub fn intersection(left: Box<dyn RayIntersect>, right: Box<dyn RayIntersect>) -> Self {
+        Csg::new(CsgOperation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Box<dyn RayIntersect>, right: Box<dyn RayIntersect>) -> Self {
+        Csg::new(CsgOperation::Difference, left, right)
+    }
+}
+
+// Borde de un tramo: true en left si viene del primer objeto, true en entering si el rayo entra
+#[derive(Debug, Clone, Copy)]
+struct Boundary {
+    distance: f32,
+    left: bool,
+    entering: bool,
+}
+
+fn boundaries(intervals: Vec<Interval>, left: bool, events: &mut Vec<Boundary>) {
+    for interval in intervals {
+        events.push(Boundary { distance: interval.enter, left, entering: true });
+        events.push(Boundary { distance: interval.exit, left, entering: false });
+    }
+}
+
+impl Csg {
+    // Bordes de entrada y salida del resultado, cada uno con el hijo del que viene
+    fn combined(&self, ray: &Ray) -> Vec<(Boundary, Boundary)> {
+        if self.bounds().is_some_and(|bounds| !bounds.hit(ray)) {
+            return Vec::new();
+        }
+
+        let left = self.left.ray_intervals(ray);
+        if left.is_empty() && self.operation != CsgOperation::Union {
+            return Vec::new();
+        }
+        let right = self.right.ray_intervals(ray);
+
+        let mut events = Vec::with_capacity(2 * (left.len() + right.len()));
+        boundaries(left, true, &mut events);
+        boundaries(right, false, &mut events);
+        events.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+
+        // Recorre los bordes en orden y abre o cierra un tramo cada vez que cambia el resultado
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut inside = false;
+        let mut enter: Option<Boundary> = None;
+        let mut result = Vec::new();
+
+        for event in events {
+            if event.left {
+                in_left = event.entering;
+            } else {
+                in_right = event.entering;
+            }
+            let now_inside = self.operation.inside(in_left, in_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            if inside {
+                enter = Some(event);
+            } else if let Some(enter) = enter.take() {
+                result.push((enter, event));
+            }
+        }
+
+        result
+    }
+
+    // Arma el impacto del borde pidiéndoselo al hijo al que pertenece
+    fn hit_on(&self, ray: &Ray, boundary: &Boundary) -> Option<Intersect<'_>> {
+        let child = if boundary.left { &self.left } else { &self.right };
+        let mut intersect = child.boundary_hit(ray, boundary.distance)?;
+        // Las caras del objeto restado quedan del revés: su normal debe apuntar fuera del resultado
+        if self.operation == CsgOperation::Difference && !boundary.left {
+            intersect.normal = -intersect.normal;
+        }
+        Some(intersect)
+    }
+}
+
+impl RayIntersect for Csg {
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        // El primer borde del resultado dentro del tramo del rayo, sea de entrada o de salida
+        let boundary = self
+            .combined(ray)
+            .into_iter()
+            .flat_map(|(enter, exit)| [enter, exit])
+            .find(|boundary| ray.contains(boundary.distance))?;
+        self.hit_on(ray, &boundary)
+    }
+
+    fn ray_intervals(&self, ray: &Ray) -> Vec<Interval> {
+        self.combined(ray)
+            .into_iter()
+            .map(|(enter, exit)| Interval { enter: enter.distance, exit: exit.distance })
+            .collect()
+    }
+
+    // El borde más cercano a la distancia pedida; con transformaciones la distancia puede no volver exacta
+    fn boundary_hit(&self, ray: &Ray, distance: f32) -> Option<Intersect<'_>> {
+        let boundary = self
+            .combined(ray)
+            .into_iter()
+            .flat_map(|(enter, exit)| [enter, exit])
+            .min_by(|a, b| (a.distance - distance).abs().total_cmp(&(b.distance - distance).abs()))?;
+        self.hit_on(ray, &boundary)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        match self.operation {
+            CsgOperation::Union => Some(self.left.bounds()?.union(&self.right.bounds()?)),
+            CsgOperation::Intersection | CsgOperation::Difference => self.left.bounds(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::material::MaterialBuilder;
+    use nalgebra_glm::Vec3;
+
+    // Dos cubos de lado 2 sobre el eje x que se solapan entre x = 0 y x = 1
+    fn pair() -> (Box<dyn RayIntersect>, Box<dyn RayIntersect>) {
+        let cube = |min_x: f32| -> Box<dyn RayIntersect> {
+            Box::new(Cube {
+                min_corner: Vec3::new(min_x, -1.0, -1.0),
+                max_corner: Vec3::new(min_x + 2.0, 1.0, 1.0),
+                material: MaterialBuilder::new().build(),
+            })
+        };
+        (cube(-1.0), cube(0.0))
+    }
+
+    // Rayo a lo largo de x que arranca en x = -5
+    fn along_x() -> Ray {
+        Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0))
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    fn assert_interval(interval: &Interval, enter: f32, exit: f32) {
+        assert_close(interval.enter, enter);
+        assert_close(interval.exit, exit);
+    }
+
+    #[test]
+    fn union_spans_both_cubes() {
+        let (left, right) = pair();
+        let csg = Csg::union(left, right);
+        let intervals = csg.ray_intervals(&along_x());
+
+        assert_eq!(intervals.len(), 1);
+        assert_interval(&intervals[0], 4.0, 7.0);
+        let hit = csg.ray_intersect(&along_x()).expect("debería chocar con la unión");
+        assert_close(hit.point.x, -1.0);
+        assert_close(hit.normal.x, -1.0);
+    }
+
+    #[test]
+    fn intersection_keeps_the_overlap() {
+        let (left, right) = pair();
+        let csg = Csg::intersection(left, right);
+        let intervals = csg.ray_intervals(&along_x());
+
+        assert_eq!(intervals.len(), 1);
+        assert_interval(&intervals[0], 5.0, 6.0);
+        // La entrada es la cara izquierda del segundo cubo
+        let hit = csg.ray_intersect(&along_x()).expect("debería chocar con la intersección");
+        assert_close(hit.point.x, 0.0);
+        assert_close(hit.normal.x, -1.0);
+    }
+
+    #[test]
+    fn difference_flips_the_carved_face() {
+        let (left, right) = pair();
+        let csg = Csg::difference(left, right);
+        let intervals = csg.ray_intervals(&along_x());
+
+        assert_eq!(intervals.len(), 1);
+        assert_interval(&intervals[0], 4.0, 5.0);
+
+        // Desde el otro lado el rayo cruza el segundo cubo sin chocar y da con la cara tallada en x = 0,
+        // cuya normal tiene que apuntar hacia afuera del resultado
+        let from_right = Ray::new(Vec3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = csg.ray_intersect(&from_right).expect("debería chocar con la cara tallada");
+        assert_close(hit.point.x, 0.0);
+        assert_close(hit.normal.x, 1.0);
+    }
+
+    #[test]
+    fn ray_starting_inside_hits_the_exit() {
+        let (left, right) = pair();
+        let csg = Csg::union(left, right);
+        let inside = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = csg.ray_intersect(&inside).expect("debería salir por la cara derecha");
+
+        assert_close(hit.distance, 1.5);
+        assert_close(hit.point.x, 2.0);
+        assert_close(hit.normal.x, 1.0);
+    }
+
+    #[test]
+    fn difference_from_inside_the_carved_region_finds_the_remaining_solid() {
+        let (left, right) = pair();
+        let csg = Csg::difference(left, right);
+        // Empieza en el hueco tallado y mira hacia el resto del primer cubo
+        let ray = Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = csg.ray_intersect(&ray).expect("debería chocar con la cara tallada");
+
+        assert_close(hit.distance, 0.5);
+        assert_close(hit.point.x, 0.0);
+        assert_close(hit.normal.x, 1.0);
+    }
+}
@@ -1,6 +1,10 @@
+use crate::bounds::Aabb;
 use crate::color::Color;
 use crate::material::Material;
-use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, Interval, RayIntersect, SurfaceSample};
+use crate::sampling::Rng;
+use crate::texture::TextureCoords;
 use nalgebra_glm::Vec3;
 
 pub struct Cube {
@@ -10,132 +14,120 @@ pub struct Cube {
 }
 
 impl RayIntersect for Cube {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
-        let mut tmin = (self.min_corner.x - ray_origin.x) / ray_direction.x;
-        let mut tmax = (self.max_corner.x - ray_origin.x) / ray_direction.x;
-        if tmin > tmax {
-            std::mem::swap(&mut tmin, &mut tmax);
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        let (tmin, tmax) = self.slab(ray)?;
+        if ray.contains(tmin) {
+            Some(self.hit_at(ray, tmin))
+        } else if tmin < ray.tmin && ray.contains(tmax) {
+            // El rayo empieza adentro de la caja: el impacto es la salida
+            Some(self.hit_at(ray, tmax))
+        } else {
+            None
         }
+    }
 
-        let mut tymin = (self.min_corner.y - ray_origin.y) / ray_direction.y;
-        let mut tymax = (self.max_corner.y - ray_origin.y) / ray_direction.y;
-        if tymin > tymax {
-            std::mem::swap(&mut tymin, &mut tymax);
+    fn ray_intervals(&self, ray: &Ray) -> Vec<Interval> {
+        match self.slab(ray) {
+            Some((enter, exit)) => vec![Interval { enter, exit }],
+            None => Vec::new(),
         }
+    }
 
-        if (tmin > tymax) || (tymin > tmax) {
-            return Intersect::empty();
-        }
+    fn boundary_hit(&self, ray: &Ray, distance: f32) -> Option<Intersect<'_>> {
+        Some(self.hit_at(ray, distance))
+    }
 
-        if tymin > tmin {
-            tmin = tymin;
-        }
-        if tymax < tmax {
-            tmax = tymax;
-        }
+    fn is_emissive(&self) -> bool {
+        self.material.is_emissive()
+    }
 
-        let mut tzmin = (self.min_corner.z - ray_origin.z) / ray_direction.z;
-        let mut tzmax = (self.max_corner.z - ray_origin.z) / ray_direction.z;
-        if tzmin > tzmax {
-            std::mem::swap(&mut tzmin, &mut tzmax);
-        }
+    fn bounds(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min_corner, self.max_corner))
+    }
 
-        if (tmin > tzmax) || (tzmin > tmax) {
-            return Intersect::empty();
-        }
+    fn surface_area(&self) -> f32 {
+        let size = self.max_corner - self.min_corner;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
 
-        if tzmin > tmin {
-            tmin = tzmin;
-        }
-        if tzmax < tmax {
-            tmax = tzmax;
+    fn sample_surface(&self, rng: &mut Rng) -> Option<SurfaceSample> {
+        let size = self.max_corner - self.min_corner;
+        let face_areas = [size.y * size.z, size.x * size.z, size.x * size.y];
+        let total: f32 = face_areas.iter().sum::<f32>() * 2.0;
+        if total <= 0.0 {
+            return None;
         }
 
-        if tmin < 0.0 {
-            return Intersect::empty();
+        // Elegir cara según su área y luego un punto uniforme en ella
+        let mut pick = rng.next_f32() * total;
+        let mut axis = 2;
+        for (index, area) in face_areas.iter().enumerate() {
+            if pick < area * 2.0 {
+                axis = index;
+                break;
+            }
+            pick -= area * 2.0;
         }
+        let positive = rng.next_f32() < 0.5;
 
-        let intersection_point = ray_origin + ray_direction * tmin;
-
-        let mut normal = self.calculate_normal(&intersection_point);
-        let (u, v) = self.get_texture_coordinates(&intersection_point);
-        let distance = tmin;
-
-        let texture_color: Color = if let Some(texture) = &self.material.texture {
-            let u_clamped = u.clamp(0.0, 1.0 - f32::EPSILON);
-            let v_clamped = v.clamp(0.0, 1.0 - f32::EPSILON);
-        
-            let tex_x = (u_clamped * texture.width() as f32) as u32;
-            let tex_y = (v_clamped * texture.height() as f32) as u32;
-        
-            let pixel = texture.get_pixel(tex_x, tex_y);
-            Color::new(pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0)
+        let mut point = self.min_corner + Vec3::new(size.x * rng.next_f32(), size.y * rng.next_f32(), size.z * rng.next_f32());
+        let mut normal = Vec3::zeros();
+        if positive {
+            point[axis] = self.max_corner[axis];
+            normal[axis] = 1.0;
         } else {
-            Color::new(0.5, 0.5, 0.5)  // Color gris
-        };
-        
-
-        // Ajustar la normal con el normal map si está disponible
-        if let Some(normal_map) = &self.material.normal_map {
-            let u_clamped = u.clamp(0.0, 1.0 - f32::EPSILON);
-            let v_clamped = v.clamp(0.0, 1.0 - f32::EPSILON);
-
-            let tex_x = (u_clamped * normal_map.width() as f32) as u32;
-            let tex_y = (v_clamped * normal_map.height() as f32) as u32;
-
-            let pixel = normal_map.get_pixel(tex_x, tex_y);
-
-            let normal_tangent = Vec3::new(
-                (pixel[0] as f32 / 255.0) * 2.0 - 1.0,
-                (pixel[1] as f32 / 255.0) * 2.0 - 1.0,
-                (pixel[2] as f32 / 255.0) * 2.0 - 1.0,
-            )
-            .normalize();
-
-            let tangent = normal.cross(&Vec3::new(0.0, 1.0, 0.0)).normalize();
-            let bitangent = normal.cross(&tangent);
-
-            normal = (tangent * normal_tangent.x
-                + bitangent * normal_tangent.y
-                + normal * normal_tangent.z)
-                .normalize();
+            point[axis] = self.min_corner[axis];
+            normal[axis] = -1.0;
         }
 
-        Intersect::new(
-            intersection_point,
+        let uv = self.get_texture_coordinates(&point);
+        let coords = TextureCoords::new(uv, point, normal, self.material.texture_mapping);
+        Some(SurfaceSample {
+            point,
             normal,
-            distance,
-            Material::new(
-                texture_color,
-                self.material.specular,
-                self.material.albedo,
-                self.material.refractive_index,
-                self.material.texture.clone(),
-                self.material.normal_map.clone(),
-                self.material.emission,
-            ),
-        )
+            uv,
+            emission: self.material.emission_at(&coords).unwrap_or(Color::black()),
+            pdf: 1.0 / total,
+        })
+    }
+
+    // Uniforme en el área, solo para puntos que están sobre alguna cara
+    fn surface_pdf(&self, point: &Vec3, _normal: &Vec3) -> f32 {
+        let epsilon = 1e-4;
+        let inside = (0..3).all(|axis| point[axis] >= self.min_corner[axis] - epsilon && point[axis] <= self.max_corner[axis] + epsilon);
+        let on_face = (0..3).any(|axis| {
+            (point[axis] - self.min_corner[axis]).abs() < epsilon || (point[axis] - self.max_corner[axis]).abs() < epsilon
+        });
+        let area = self.surface_area();
+        if inside && on_face && area > 0.0 { 1.0 / area } else { 0.0 }
     }
 }
 
 
 impl Cube {
+    fn slab(&self, ray: &Ray) -> Option<(f32, f32)> {
+        Aabb::new(self.min_corner, self.max_corner).slab(ray)
+    }
+
+    // Intersección en el parámetro t del rayo; el material se evalúa después, al sombrear
+    fn hit_at(&self, ray: &Ray, t: f32) -> Intersect<'_> {
+        let intersection_point = ray.at(t);
+
+        let normal = self.calculate_normal(&intersection_point);
+        let (tangent, bitangent) = self.calculate_tangents(&intersection_point);
+        let uv = self.get_texture_coordinates(&intersection_point);
+
+        Intersect::new(intersection_point, normal, t, uv, &self.material).with_tangents(tangent, bitangent)
+    }
+
     fn get_texture_coordinates(&self, point: &Vec3) -> (f32, f32) {
         let epsilon = 1e-4;
     
-        if (point.x - self.min_corner.x).abs() < epsilon {
-            let u = (point.z - self.min_corner.z) / (self.max_corner.z - self.min_corner.z);
-            let v = (self.max_corner.y - point.y) / (self.max_corner.y - self.min_corner.y); 
-            (u, v)
-        } else if (point.x - self.max_corner.x).abs() < epsilon {
+        if (point.x - self.min_corner.x).abs() < epsilon || (point.x - self.max_corner.x).abs() < epsilon {
             let u = (point.z - self.min_corner.z) / (self.max_corner.z - self.min_corner.z);
             let v = (self.max_corner.y - point.y) / (self.max_corner.y - self.min_corner.y); 
             (u, v)
-        } else if (point.y - self.min_corner.y).abs() < epsilon {
-            let u = (point.x - self.min_corner.x) / (self.max_corner.x - self.min_corner.x);
-            let v = (point.z - self.min_corner.z) / (self.max_corner.z - self.min_corner.z);
-            (u, v)
-        } else if (point.y - self.max_corner.y).abs() < epsilon {
+        } else if (point.y - self.min_corner.y).abs() < epsilon || (point.y - self.max_corner.y).abs() < epsilon {
             let u = (point.x - self.min_corner.x) / (self.max_corner.x - self.min_corner.x);
             let v = (point.z - self.min_corner.z) / (self.max_corner.z - self.min_corner.z);
             (u, v)
@@ -153,6 +145,21 @@ impl Cube {
 
 
 
+    // Direcciones en las que crecen u y v sobre cada cara, igual que en get_texture_coordinates
+    fn calculate_tangents(&self, point: &Vec3) -> (Vec3, Vec3) {
+        let epsilon = 1e-4;
+
+        if (point.x - self.min_corner.x).abs() < epsilon || (point.x - self.max_corner.x).abs() < epsilon {
+            (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0))
+        } else if (point.y - self.min_corner.y).abs() < epsilon || (point.y - self.max_corner.y).abs() < epsilon {
+            (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0))
+        } else if (point.z - self.min_corner.z).abs() < epsilon {
+            (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0))
+        } else {
+            (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0))
+        }
+    }
+
     fn calculate_normal(&self, point: &Vec3) -> Vec3 {
         let epsilon = 1e-4;
     
@@ -174,3 +181,159 @@ impl Cube {
     
     
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialBuilder;
+
+    fn unit_cube() -> Cube {
+        Cube {
+            min_corner: Vec3::new(-1.0, -1.0, -1.0),
+            max_corner: Vec3::new(1.0, 1.0, 1.0),
+            material: MaterialBuilder::new().build(),
+        }
+    }
+
+    fn assert_close(a: &Vec3, b: &Vec3) {
+        assert!((a - b).magnitude() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn hits_front_face_from_outside() {
+        let cube = unit_cube();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cube.ray_intersect(&ray).expect("debería chocar con la cara frontal");
+
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert_close(&hit.point, &Vec3::new(0.0, 0.0, 1.0));
+        assert_close(&hit.normal, &Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ray_inside_returns_exit_hit() {
+        let cube = unit_cube();
+        let ray = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cube.ray_intersect(&ray).expect("desde adentro debería devolver la salida");
+
+        assert!((hit.distance - 1.0).abs() < 1e-5);
+        assert_close(&hit.point, &Vec3::new(1.0, 0.5, 0.0));
+        assert_close(&hit.normal, &Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn axis_parallel_ray_along_face_plane_hits_without_nan() {
+        let cube = unit_cube();
+        // El origen está justo en el plano y = 1, donde 0 * infinito daba NaN
+        let ray = Ray::new(Vec3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cube.ray_intersect(&ray).expect("el rayo roza la cara superior");
+
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert!(hit.point.iter().all(|c| c.is_finite()));
+        assert!(hit.normal.iter().all(|c| c.is_finite()));
+    }
+
+    #[test]
+    fn axis_parallel_ray_outside_slab_misses() {
+        let cube = unit_cube();
+        let ray = Ray::new(Vec3::new(-5.0, 1.001, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cube.ray_intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn ray_through_edge_hits_at_edge() {
+        let cube = unit_cube();
+        // Apunta a la arista x = 1, z = 1 en diagonal
+        let ray = Ray::new(Vec3::new(3.0, 0.25, 3.0), Vec3::new(-1.0, 0.0, -1.0).normalize());
+        let hit = cube.ray_intersect(&ray).expect("debería chocar con la arista");
+
+        assert!((hit.distance - 2.0 * 2.0_f32.sqrt()).abs() < 1e-4);
+        assert_close(&hit.point, &Vec3::new(1.0, 0.25, 1.0));
+        assert!((hit.normal.magnitude() - 1.0).abs() < 1e-5);
+        assert!(hit.normal.x == 1.0 || hit.normal.z == 1.0);
+    }
+
+    #[test]
+    fn axis_parallel_ray_along_edge_hits() {
+        let cube = unit_cube();
+        // Recorre la arista y = 1, z = 1: dos componentes de la dirección son cero
+        let ray = Ray::new(Vec3::new(-4.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cube.ray_intersect(&ray).expect("el rayo recorre la arista");
+
+        assert!((hit.distance - 3.0).abs() < 1e-5);
+        assert_close(&hit.point, &Vec3::new(-1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn ray_through_corner_hits_at_corner() {
+        let cube = unit_cube();
+        let ray = Ray::new(Vec3::new(3.0, 3.0, 3.0), Vec3::new(-1.0, -1.0, -1.0).normalize());
+        let hit = cube.ray_intersect(&ray).expect("debería chocar con la esquina");
+
+        assert!((hit.distance - 2.0 * 3.0_f32.sqrt()).abs() < 1e-4);
+        assert_close(&hit.point, &Vec3::new(1.0, 1.0, 1.0));
+        assert!(hit.normal.iter().all(|c| c.is_finite()));
+    }
+
+    #[test]
+    fn misses_box_behind_or_beyond_tmax() {
+        let cube = unit_cube();
+        let away = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(cube.ray_intersect(&away).is_none());
+
+        let short = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)).with_range(0.0, 3.0);
+        assert!(cube.ray_intersect(&short).is_none());
+    }
+
+    #[test]
+    fn tangents_follow_uv_on_every_face() {
+        let cube = unit_cube();
+        let step = 0.1;
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                // Rayo hacia el centro de la cara, un poco corrido para no caer en una arista
+                let mut origin = Vec3::new(0.2, 0.3, 0.1);
+                origin[axis] = sign * 5.0;
+                let mut direction = Vec3::zeros();
+                direction[axis] = -sign;
+                let hit = cube.ray_intersect(&Ray::new(origin, direction)).expect("debería chocar con la cara");
+
+                // Moverse por la tangente solo cambia u, y por la bitangente solo v, ambas hacia arriba
+                let (u, v) = hit.uv;
+                let (u_t, v_t) = cube.get_texture_coordinates(&(hit.point + hit.tangent * step));
+                let (u_b, v_b) = cube.get_texture_coordinates(&(hit.point + hit.bitangent * step));
+                let face = format!("eje {} signo {}", axis, sign);
+                assert!((u_t - u - step / 2.0).abs() < 1e-5 && (v_t - v).abs() < 1e-5, "{}", face);
+                assert!((v_b - v - step / 2.0).abs() < 1e-5 && (u_b - u).abs() < 1e-5, "{}", face);
+                assert!(hit.tangent.dot(&hit.normal).abs() < 1e-6 && hit.bitangent.dot(&hit.normal).abs() < 1e-6, "{}", face);
+            }
+        }
+    }
+
+    #[test]
+    fn spawned_ray_skips_the_face_it_starts_on() {
+        let cube = unit_cube();
+        let hit = cube
+            .ray_intersect(&Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)))
+            .expect("debería chocar con la cara frontal");
+
+        assert!(cube.ray_intersect(&hit.spawn_ray(&Vec3::new(1.0, 0.0, 1.0).normalize())).is_none());
+        let exit = cube.ray_intersect(&hit.spawn_ray(&Vec3::new(0.0, 0.0, -1.0))).expect("debería salir por la cara trasera");
+        assert_close(&exit.point, &Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn intervals_cover_the_box_behind_the_origin() {
+        let cube = unit_cube();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let intervals = cube.ray_intervals(&ray);
+
+        assert_eq!(intervals.len(), 1);
+        assert!((intervals[0].enter + 1.0).abs() < 1e-5);
+        assert!((intervals[0].exit - 1.0).abs() < 1e-5);
+        let enter = cube.boundary_hit(&ray, intervals[0].enter).expect("el borde de entrada existe");
+        let exit = cube.boundary_hit(&ray, intervals[0].exit).expect("el borde de salida existe");
+        assert_close(&enter.normal, &Vec3::new(0.0, -1.0, 0.0));
+        assert_close(&exit.normal, &Vec3::new(0.0, 1.0, 0.0));
+    }
+}
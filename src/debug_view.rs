@@ -0,0 +1,161 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::color::Color;
+use crate::integrator::Integrator;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::scene::Scene;
+
+// Vistas de depuración (AOVs): muestran un solo dato de la intersección primaria
+
+pub struct NormalsIntegrator;
+
+impl Integrator for NormalsIntegrator {
+    fn name(&self) -> &str {
+        "Normals"
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, _rng: &mut Rng) -> Color {
+        let Some(intersect) = scene.intersect(ray) else {
+            return Color::black();
+        };
+
+        let n = intersect.normal;
+        Color::new(n.x * 0.5 + 0.5, n.y * 0.5 + 0.5, n.z * 0.5 + 0.5)
+    }
+}
+
+pub struct UvIntegrator;
+
+impl Integrator for UvIntegrator {
+    fn name(&self) -> &str {
+        "UV"
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, _rng: &mut Rng) -> Color {
+        let Some(intersect) = scene.intersect(ray) else {
+            return Color::black();
+        };
+
+        // u en rojo, v en verde; azul marca coordenadas fuera de [0, 1]
+        let (u, v) = intersect.uv;
+        let outside = !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v);
+        Color::new(u, v, if outside { 1.0 } else { 0.0 })
+    }
+}
+
+pub struct DepthIntegrator {
+    pub max_distance: f32,
+}
+
+impl DepthIntegrator {
+    pub fn new(max_distance: f32) -> Self {
+        DepthIntegrator { max_distance }
+    }
+}
+
+impl Integrator for DepthIntegrator {
+    fn name(&self) -> &str {
+        "Depth"
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, _rng: &mut Rng) -> Color {
+        let Some(intersect) = scene.intersect(ray) else {
+            return Color::black();
+        };
+
+        // Blanco cerca de la cámara, negro en max_distance
+        let depth = 1.0 - (intersect.distance / self.max_distance).clamp(0.0, 1.0);
+        Color::new(depth, depth, depth)
+    }
+}
+
+pub struct MaterialIdIntegrator;
+
+// Los materiales se clonan por cubo, así que se identifican por su textura y sus parámetros
+fn material_key(material: &Material) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    material.texture.as_ref().map(|texture| Rc::as_ptr(texture) as *const () as usize).hash(&mut hasher);
+    material.normal_map.as_ref().map(|texture| Rc::as_ptr(texture) as *const () as usize).hash(&mut hasher);
+    for value in [material.diffuse_weight, material.specular_weight, material.reflectivity, material.roughness, material.transmission] {
+        value.to_bits().hash(&mut hasher);
+    }
+    material.specular.to_bits().hash(&mut hasher);
+    material.refractive_index.to_bits().hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Integrator for MaterialIdIntegrator {
+    fn name(&self) -> &str {
+        "Material ID"
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, _rng: &mut Rng) -> Color {
+        let Some(intersect) = scene.intersect(ray) else {
+            return Color::black();
+        };
+
+        let key = material_key(intersect.material);
+        Color::from_u8((key >> 16) as u8, (key >> 8) as u8, key as u8)
+    }
+}
+
+pub struct AlbedoIntegrator;
+
+impl Integrator for AlbedoIntegrator {
+    fn name(&self) -> &str {
+        "Albedo"
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, _rng: &mut Rng) -> Color {
+        let Some(intersect) = scene.intersect(ray) else {
+            return Color::black();
+        };
+
+        // Color de la textura sin ninguna iluminación
+        intersect.diffuse()
+    }
+}
+
+// Mapa de calor con la cantidad de pruebas de intersección que hizo otro integrador por pixel
+pub struct HeatmapIntegrator {
+    pub inner: Box<dyn Integrator>,
+    pub max_tests: u64,
+}
+
+impl HeatmapIntegrator {
+    pub fn new(inner: Box<dyn Integrator>, max_tests: u64) -> Self {
+        HeatmapIntegrator { inner, max_tests }
+    }
+}
+
+impl Integrator for HeatmapIntegrator {
+    fn name(&self) -> &str {
+        "Intersection heatmap"
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, rng: &mut Rng) -> Color {
+        scene.reset_intersection_tests();
+        self.inner.radiance(scene, ray, rng);
+        let t = (scene.intersection_tests() as f32 / self.max_tests as f32).clamp(0.0, 1.0);
+
+        heat_color(t)
+    }
+}
+
+// Rampa azul -> verde -> amarillo -> rojo
+fn heat_color(t: f32) -> Color {
+    let stops = [
+        Color::new(0.0, 0.0, 0.5),
+        Color::new(0.0, 0.8, 0.2),
+        Color::new(1.0, 1.0, 0.0),
+        Color::new(1.0, 0.0, 0.0),
+    ];
+    let scaled = t * (stops.len() - 1) as f32;
+    let index = (scaled.floor() as usize).min(stops.len() - 2);
+    let local = scaled - index as f32;
+    stops[index] * (1.0 - local) + stops[index + 1] * local
+}
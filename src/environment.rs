@@ -0,0 +1,279 @@
+use crate::color::Color;
+use crate::ray::Ray;
+use image::{GenericImageView, ImageError};
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Nivel de mip de un mapa equirectangular
+struct EnvironmentLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl EnvironmentLevel {
+    fn texel(&self, x: usize, y: usize) -> Color {
+        self.texels[y * self.width + x]
+    }
+
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (x * 2).min(self.width - 1);
+                let x1 = (x * 2 + 1).min(self.width - 1);
+                let y0 = (y * 2).min(self.height - 1);
+                let y1 = (y * 2 + 1).min(self.height - 1);
+
+                let sum = self.texel(x0, y0) + self.texel(x1, y0) + self.texel(x0, y1) + self.texel(x1, y1);
+                texels.push(sum * 0.25);
+            }
+        }
+
+        EnvironmentLevel { width, height, texels }
+    }
+
+    fn sample_bilinear(&self, u: f32, v: f32) -> Color {
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let tx = x - x0;
+        let ty = y - y0;
+
+        // En horizontal el mapa da la vuelta, en vertical se recorta
+        let wrap = |i: i32| i.rem_euclid(self.width as i32) as usize;
+        let xa = wrap(x0 as i32);
+        let xb = wrap(x0 as i32 + 1);
+        let ya = y0 as usize;
+        let yb = (ya + 1).min(self.height - 1);
+
+        let top = self.texel(xa, ya) * (1.0 - tx) + self.texel(xb, ya) * tx;
+        let bottom = self.texel(xa, yb) * (1.0 - tx) + self.texel(xb, yb) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+// Los niveles y los armónicos se guardan en luz lineal para que los promedios sean correctos;
+// al leerlos se vuelven a codificar en sRGB, que es como trabaja el resto del render
+pub struct EnvironmentMap {
+    levels: Vec<EnvironmentLevel>,
+    irradiance_sh: [Color; 9],
+    pub intensity: f32,
+}
+
+impl EnvironmentMap {
+    // Los texeles llegan codificados en sRGB, como en las imágenes
+    pub fn new(width: usize, height: usize, texels: Vec<Color>) -> Self {
+        let texels = texels.into_iter().map(Color::srgb_to_linear).collect();
+        let mut levels = vec![EnvironmentLevel { width, height, texels }];
+        while levels.last().is_some_and(|level| level.width > 1 || level.height > 1) {
+            let next = levels.last().unwrap().downsample();
+            levels.push(next);
+        }
+
+        let irradiance_sh = project_irradiance(&levels);
+
+        EnvironmentMap {
+            levels,
+            irradiance_sh,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn from_image(image_path: &str) -> Result<Self, ImageError> {
+        let image_data = image::open(image_path)?;
+
+        let (width, height) = image_data.dimensions();
+        let texels = image_data
+            .pixels()
+            .map(|(_, _, pixel)| Color::from_u8(pixel[0], pixel[1], pixel[2]))
+            .collect();
+
+        Ok(EnvironmentMap::new(width as usize, height as usize, texels))
+    }
+
+    // Cielo procedural con degradado entre cenit, horizonte y suelo
+    pub fn gradient(zenith: Color, horizon: Color, ground: Color) -> Self {
+        let width = 256;
+        let height = 128;
+        let mut texels = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            let theta = (y as f32 + 0.5) / height as f32 * PI;
+            let elevation = theta.cos();
+            let color = if elevation >= 0.0 {
+                let t = elevation.sqrt();
+                horizon * (1.0 - t) + zenith * t
+            } else {
+                let t = (-elevation * 4.0).min(1.0);
+                horizon * (1.0 - t) + ground * t
+            };
+
+            for _ in 0..width {
+                texels.push(color);
+            }
+        }
+
+        EnvironmentMap::new(width, height, texels)
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    // Color del fondo en la dirección dada
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        self.sample_lod(direction, 0.0)
+    }
+
+    // Fondo visto por un rayo de cámara: con diferenciales se elige el mip que corresponde a lo que cubre el pixel
+    pub fn background(&self, ray: &Ray) -> Color {
+        let Some(differentials) = &ray.differentials else {
+            return self.sample(&ray.direction);
+        };
+        let angle = |other: &Vec3| ray.direction.cross(other).magnitude().atan2(ray.direction.dot(other));
+        let footprint = angle(&differentials.x_direction).max(angle(&differentials.y_direction));
+        // Cada texel del nivel 0 cubre PI / alto radianes en vertical
+        let texel = PI / self.levels[0].height as f32;
+        self.sample_lod(&ray.direction, (footprint / texel).max(1.0).log2())
+    }
+
+    // Radiancia del entorno usada como fuente de luz
+    pub fn radiance(&self, direction: &Vec3) -> Color {
+        self.sample(direction) * self.intensity
+    }
+
+    pub fn sample_lod(&self, direction: &Vec3, lod: f32) -> Color {
+        let (u, v) = direction_to_uv(direction);
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+        let lower = lod.floor() as usize;
+        let upper = (lower + 1).min(self.levels.len() - 1);
+        let t = lod - lower as f32;
+
+        let a = self.levels[lower].sample_bilinear(u, v);
+        if t == 0.0 || lower == upper {
+            return a.linear_to_srgb();
+        }
+        let b = self.levels[upper].sample_bilinear(u, v);
+        (a * (1.0 - t) + b * t).linear_to_srgb()
+    }
+
+    // Radiancia difusa (irradiancia / PI) a partir de los armónicos esféricos
+    pub fn irradiance(&self, normal: &Vec3) -> Color {
+        let basis = sh_basis(normal);
+        let mut result = Color::black();
+        for (coefficient, y) in self.irradiance_sh.iter().zip(basis.iter()) {
+            result = result + *coefficient * *y;
+        }
+        (result * (1.0 / PI)).linear_to_srgb() * self.intensity
+    }
+
+    // Reflejo brillante desde el mip que corresponde a la rugosidad. Los mips son promedios de 2x2
+    // (filtro caja), no una convolución con el lóbulo GGX: el desenfoque es solo una aproximación
+    pub fn specular(&self, reflect_dir: &Vec3, roughness: f32) -> Color {
+        // Se dejan fuera los últimos niveles, que ya son casi un solo color
+        let max_lod = (self.levels.len() as f32 - 3.0).max(0.0);
+        self.sample_lod(reflect_dir, roughness.clamp(0.0, 1.0).sqrt() * max_lod) * self.intensity
+    }
+}
+
+fn direction_to_uv(direction: &Vec3) -> (f32, f32) {
+    let d = direction.normalize();
+    let u = 0.5 + d.z.atan2(d.x) / (2.0 * PI);
+    let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+fn uv_to_direction(u: f32, v: f32) -> Vec3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin())
+}
+
+fn sh_basis(n: &Vec3) -> [f32; 9] {
+    [
+        0.282095,
+        0.488603 * n.y,
+        0.488603 * n.z,
+        0.488603 * n.x,
+        1.092548 * n.x * n.y,
+        1.092548 * n.y * n.z,
+        0.315392 * (3.0 * n.z * n.z - 1.0),
+        1.092548 * n.x * n.z,
+        0.546274 * (n.x * n.x - n.y * n.y),
+    ]
+}
+
+// Proyecta el entorno en 9 coeficientes y aplica la convolución coseno (Ramamoorthi y Hanrahan)
+fn project_irradiance(levels: &[EnvironmentLevel]) -> [Color; 9] {
+    let level = levels
+        .iter()
+        .find(|level| level.width <= 64)
+        .unwrap_or(levels.last().unwrap());
+
+    let mut coefficients = [Color::black(); 9];
+    let texel_phi = 2.0 * PI / level.width as f32;
+    let texel_theta = PI / level.height as f32;
+
+    for y in 0..level.height {
+        let v = (y as f32 + 0.5) / level.height as f32;
+        let solid_angle = texel_phi * texel_theta * (v * PI).sin();
+
+        for x in 0..level.width {
+            let u = (x as f32 + 0.5) / level.width as f32;
+            let direction = uv_to_direction(u, v);
+            let radiance = level.texel(x, y) * solid_angle;
+
+            for (coefficient, y) in coefficients.iter_mut().zip(sh_basis(&direction).iter()) {
+                *coefficient = *coefficient + radiance * *y;
+            }
+        }
+    }
+
+    let band_scale = [PI, 2.0 * PI / 3.0, 2.0 * PI / 3.0, 2.0 * PI / 3.0, PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0];
+    for (coefficient, scale) in coefficients.iter_mut().zip(band_scale.iter()) {
+        *coefficient = *coefficient * *scale;
+    }
+
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_close(a: Color, b: Color, tolerance: f32) {
+        assert!(
+            (a.r - b.r).abs() < tolerance && (a.g - b.g).abs() < tolerance && (a.b - b.b).abs() < tolerance,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn constant_environment_lights_with_its_color() {
+        let color = Color::new(0.8, 0.5, 0.2);
+        let environment = EnvironmentMap::new(64, 32, vec![color; 64 * 32]);
+
+        let directions = [
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-0.3, 0.5, 0.8).normalize(),
+        ];
+        for direction in directions {
+            assert_color_close(environment.irradiance(&direction), color, 1e-3);
+            for step in 0..=10 {
+                let roughness = step as f32 / 10.0;
+                assert_color_close(environment.specular(&direction, roughness), color, 1e-4);
+            }
+        }
+    }
+}
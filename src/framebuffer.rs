@@ -1,3 +1,4 @@
+use crate::color::Color;
 
 pub struct Framebuffer {
     pub width: usize,
@@ -37,4 +38,42 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
+
+    pub fn save(&self, path: &str) -> image::ImageResult<()> {
+        let mut image = image::RgbImage::new(self.width as u32, self.height as u32);
+        for (index, pixel) in self.buffer.iter().enumerate() {
+            let x = (index % self.width) as u32;
+            let y = (index / self.width) as u32;
+            image.put_pixel(x, y, image::Rgb([(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8]));
+        }
+        image.save(path)
+    }
+}
+
+// Suma de muestras por pixel para el render progresivo
+pub struct Accumulator {
+    samples: Vec<Color>,
+    pub frames: u32,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Self {
+        Accumulator {
+            samples: vec![Color::black(); width * height],
+            frames: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for sample in self.samples.iter_mut() {
+            *sample = Color::black();
+        }
+        self.frames = 0;
+    }
+
+    // Agrega una muestra y devuelve el promedio actual del pixel
+    pub fn add(&mut self, index: usize, sample: Color) -> Color {
+        self.samples[index] = self.samples[index] + sample;
+        self.samples[index] * (1.0 / (self.frames + 1) as f32)
+    }
 }
@@ -0,0 +1,343 @@
+use image::error::{ParameterError, ParameterErrorKind};
+use image::ImageError;
+use nalgebra_glm::Vec3;
+
+use crate::bounds::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::ray_intersect::{count_intersection_test, Intersect, RayIntersect};
+use crate::texture::check_dimensions;
+
+// Terreno a partir de una grilla de alturas en [0, 1]; cada celda son dos triángulos.
+// Ocupa la caja que empieza en min_corner con el tamaño dado (size.y es la altura máxima)
+pub struct Heightfield {
+    pub min_corner: Vec3,
+    pub size: Vec3,
+    pub material: Material,
+    // Cantidad de vértices en X y en Z
+    columns: usize,
+    rows: usize,
+    heights: Vec<f32>,
+    // Normales por vértice para sombrear suave entre triángulos
+    normals: Vec<Vec3>,
+}
+
+impl Heightfield {
+    // Error si la grilla tiene menos de 2x2 alturas o si la cantidad no coincide con columns * rows
+    pub fn new(
+        heights: Vec<f32>,
+        columns: usize,
+        rows: usize,
+        min_corner: Vec3,
+        size: Vec3,
+        material: Material,
+    ) -> Result<Self, ImageError> {
+        check_grid(columns, rows)?;
+        if heights.len() != columns * rows {
+            return Err(ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch)));
+        }
+
+        let mut heightfield = Heightfield {
+            min_corner,
+            size,
+            material,
+            columns,
+            rows,
+            heights,
+            normals: Vec::new(),
+        };
+        heightfield.normals = (0..rows)
+            .flat_map(|j| (0..columns).map(move |i| (i, j)))
+            .map(|(i, j)| heightfield.vertex_normal(i, j))
+            .collect();
+        Ok(heightfield)
+    }
+
+    // Alturas desde una imagen en escala de grises: negro es el fondo de la caja y blanco el techo
+    #[allow(dead_code)]  // La escena de ejemplo usa terreno procedural
+    pub fn from_image(path: &str, min_corner: Vec3, size: Vec3, material: Material) -> Result<Self, ImageError> {
+        let gray = image::open(path)?.to_luma8();
+        let (width, height) = gray.dimensions();
+        let heights = gray.pixels().map(|pixel| pixel.0[0] as f32 / 255.0).collect();
+        Heightfield::new(heights, width as usize, height as usize, min_corner, size, material)
+    }
+
+    // Alturas desde una función de (u, v) en [0, 1], por ejemplo ruido procedural
+    pub fn from_fn(
+        columns: usize,
+        rows: usize,
+        min_corner: Vec3,
+        size: Vec3,
+        material: Material,
+        height: impl Fn(f32, f32) -> f32,
+    ) -> Result<Self, ImageError> {
+        // Antes de dividir por columns - 1 y rows - 1
+        check_grid(columns, rows)?;
+        let heights = (0..rows)
+            .flat_map(|j| (0..columns).map(move |i| (i, j)))
+            .map(|(i, j)| height(i as f32 / (columns - 1) as f32, j as f32 / (rows - 1) as f32).clamp(0.0, 1.0))
+            .collect();
+        Heightfield::new(heights, columns, rows, min_corner, size, material)
+    }
+
+    fn cell_size(&self) -> (f32, f32) {
+        (self.size.x / (self.columns - 1) as f32, self.size.z / (self.rows - 1) as f32)
+    }
+
+    fn height(&self, i: usize, j: usize) -> f32 {
+        self.heights[j * self.columns + i]
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Vec3 {
+        let (cell_x, cell_z) = self.cell_size();
+        self.min_corner + Vec3::new(i as f32 * cell_x, self.height(i, j) * self.size.y, j as f32 * cell_z)
+    }
+
+    // Pendiente por diferencias centrales (hacia un lado en los bordes)
+    fn vertex_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (left, right) = (i.saturating_sub(1), (i + 1).min(self.columns - 1));
+        let (back, front) = (j.saturating_sub(1), (j + 1).min(self.rows - 1));
+        let (cell_x, cell_z) = self.cell_size();
+
+        let slope_x = (self.height(right, j) - self.height(left, j)) * self.size.y / ((right - left) as f32 * cell_x);
+        let slope_z = (self.height(i, front) - self.height(i, back)) * self.size.y / ((front - back) as f32 * cell_z);
+        Vec3::new(-slope_x, 1.0, -slope_z).normalize()
+    }
+
+    fn extent(&self) -> Aabb {
+        Aabb::new(self.min_corner, self.min_corner + self.size)
+    }
+
+    // Prueba los dos triángulos de la celda; devuelve t y la normal interpolada
+    fn intersect_cell(&self, i: usize, j: usize, ray: &Ray) -> Option<(f32, Vec3)> {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let mut closest: Option<(f32, Vec3)> = None;
+
+        for triangle in [[0, 1, 2], [0, 2, 3]] {
+            let [a, b, c] = triangle.map(|k| corners[k]);
+            let Some((t, u, v)) = intersect_triangle(
+                &self.vertex(a.0, a.1),
+                &self.vertex(b.0, b.1),
+                &self.vertex(c.0, c.1),
+                ray,
+            ) else {
+                continue;
+            };
+            if closest.is_none_or(|(closest_t, _)| t < closest_t) {
+                let normal = self.normals[a.1 * self.columns + a.0] * (1.0 - u - v)
+                    + self.normals[b.1 * self.columns + b.0] * u
+                    + self.normals[c.1 * self.columns + c.0] * v;
+                closest = Some((t, normal.normalize()));
+            }
+        }
+
+        closest
+    }
+
+    fn hit_at(&self, ray: &Ray, t: f32, normal: Vec3) -> Intersect<'_> {
+        let point = ray.at(t);
+        let uv = (
+            ((point.x - self.min_corner.x) / self.size.x).clamp(0.0, 1.0),
+            ((point.z - self.min_corner.z) / self.size.z).clamp(0.0, 1.0),
+        );
+
+        // u crece hacia +X y v hacia +Z, proyectados sobre la pendiente
+        let x_axis = Vec3::new(1.0, 0.0, 0.0);
+        let z_axis = Vec3::new(0.0, 0.0, 1.0);
+        let tangent = (x_axis - normal * normal.dot(&x_axis)).normalize();
+        let bitangent = (z_axis - normal * normal.dot(&z_axis)).normalize();
+
+        Intersect::new(point, normal, t, uv, &self.material).with_tangents(tangent, bitangent)
+    }
+}
+
+impl RayIntersect for Heightfield {
+    // Recorre solo las celdas que cruza la proyección del rayo sobre XZ (DDA), de la más cercana a la más lejana
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        let (tmin, tmax) = self.extent().ray_range(ray)?;
+
+        let (cell_x, cell_z) = self.cell_size();
+        let entry = ray.at(tmin);
+        let mut i = (((entry.x - self.min_corner.x) / cell_x).floor().max(0.0) as usize).min(self.columns - 2);
+        let mut j = (((entry.z - self.min_corner.z) / cell_z).floor().max(0.0) as usize).min(self.rows - 2);
+
+        // Parámetro del próximo borde de celda en cada eje y cuánto avanza t por celda
+        let next_border = |origin: f32, direction: f32, min: f32, cell: f32, index: usize| -> (f32, f32) {
+            if direction > 0.0 {
+                ((min + (index + 1) as f32 * cell - origin) / direction, cell / direction)
+            } else if direction < 0.0 {
+                ((min + index as f32 * cell - origin) / direction, -cell / direction)
+            } else {
+                (f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (mut next_x, delta_x) = next_border(ray.origin.x, ray.direction.x, self.min_corner.x, cell_x, i);
+        let (mut next_z, delta_z) = next_border(ray.origin.z, ray.direction.z, self.min_corner.z, cell_z, j);
+
+        let mut cell_enter = tmin;
+        while cell_enter <= tmax {
+            let cell_exit = next_x.min(next_z).min(tmax);
+
+            // Si el rayo pasa por encima del vértice más alto de la celda no hace falta probar los triángulos
+            let ray_low = ray.at(cell_enter).y.min(ray.at(cell_exit).y);
+            let cell_top = self.height(i, j).max(self.height(i + 1, j)).max(self.height(i, j + 1)).max(self.height(i + 1, j + 1));
+            if ray_low <= self.min_corner.y + cell_top * self.size.y {
+                if let Some((t, normal)) = self.intersect_cell(i, j, ray) {
+                    return Some(self.hit_at(ray, t, normal));
+                }
+            }
+
+            if next_x < next_z {
+                if ray.direction.x > 0.0 && i + 2 < self.columns {
+                    i += 1;
+                } else if ray.direction.x < 0.0 && i > 0 {
+                    i -= 1;
+                } else {
+                    break;
+                }
+                cell_enter = next_x;
+                next_x += delta_x;
+            } else {
+                if ray.direction.z > 0.0 && j + 2 < self.rows {
+                    j += 1;
+                } else if ray.direction.z < 0.0 && j > 0 {
+                    j -= 1;
+                } else {
+                    break;
+                }
+                cell_enter = next_z;
+                next_z += delta_z;
+            }
+        }
+
+        None
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.extent())
+    }
+}
+
+// Cada celda necesita cuatro vértices: menos de 2x2 alturas es un error y no un pánico
+fn check_grid(columns: usize, rows: usize) -> Result<(), ImageError> {
+    let to_u32 = |count: usize| u32::try_from(count).unwrap_or(u32::MAX);
+    check_dimensions(to_u32(columns), to_u32(rows), 2)
+}
+
+// Möller-Trumbore: t y coordenadas baricéntricas de b y c
+fn intersect_triangle(a: &Vec3, b: &Vec3, c: &Vec3, ray: &Ray) -> Option<(f32, f32, f32)> {
+    count_intersection_test();
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = ray.direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+    if determinant.abs() < 1e-9 {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let to_origin = ray.origin - a;
+    let u = to_origin.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = to_origin.cross(&edge1);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&q) * inverse;
+    if ray.contains(t) { Some((t, u, v)) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialBuilder;
+
+    #[test]
+    fn from_image_rejects_images_smaller_than_two_by_two() {
+        let path = std::env::temp_dir().join("heightfield_1x4.png");
+        image::GrayImage::new(1, 4).save(&path).expect("no se pudo guardar la imagen de prueba");
+        let result = Heightfield::from_image(path.to_str().unwrap(), Vec3::zeros(), Vec3::new(1.0, 1.0, 1.0), MaterialBuilder::new().build());
+        let _ = std::fs::remove_file(&path);
+
+        assert!(matches!(result, Err(ImageError::Parameter(_))));
+    }
+
+    fn terrain(columns: usize, rows: usize, height: impl Fn(f32, f32) -> f32) -> Result<Heightfield, ImageError> {
+        Heightfield::from_fn(columns, rows, Vec3::new(-2.0, 0.0, -1.5), Vec3::new(4.0, 1.0, 3.0), MaterialBuilder::new().build(), height)
+    }
+
+    fn bumpy() -> Heightfield {
+        terrain(12, 9, |u, v| 0.5 + 0.3 * (u * 9.0).sin() * (v * 7.0).cos()).expect("la grilla es válida")
+    }
+
+    // Rayos que bajan hacia el terreno desde distintos lados y con distinta inclinación
+    fn rays() -> impl Iterator<Item = Ray> {
+        (0..400).map(|k| {
+            let k = k as f32;
+            let origin = Vec3::new((k * 0.61).sin() * 3.0, 1.5 + (k * 0.17).cos(), (k * 0.43).cos() * 2.5);
+            let target = Vec3::new((k * 0.29).cos() * 2.2, 0.3, (k * 0.83).sin() * 1.7);
+            Ray::new(origin, (target - origin).normalize())
+        })
+    }
+
+    #[test]
+    fn grids_smaller_than_two_by_two_are_errors() {
+        assert!(terrain(1, 5, |_, _| 0.5).is_err());
+        assert!(terrain(5, 1, |_, _| 0.5).is_err());
+        let material = MaterialBuilder::new().build();
+        assert!(Heightfield::new(vec![0.0; 5], 2, 2, Vec3::zeros(), Vec3::new(1.0, 1.0, 1.0), material).is_err());
+    }
+
+    #[test]
+    fn dda_matches_testing_every_cell() {
+        let heightfield = bumpy();
+        let mut hits = 0;
+        for ray in rays() {
+            let brute = (0..heightfield.rows - 1)
+                .flat_map(|j| (0..heightfield.columns - 1).map(move |i| (i, j)))
+                .filter_map(|(i, j)| heightfield.intersect_cell(i, j, &ray))
+                .map(|(t, _)| t)
+                .min_by(f32::total_cmp);
+            let dda = heightfield.ray_intersect(&ray).map(|hit| hit.distance);
+            match (brute, dda) {
+                (Some(expected), Some(found)) => {
+                    assert!((expected - found).abs() < 1e-4, "{} != {}", expected, found);
+                    hits += 1;
+                }
+                (None, None) => {}
+                other => panic!("{:?} con {:?}", other, ray),
+            }
+        }
+        assert!(hits > 200, "{}", hits);
+    }
+
+    #[test]
+    fn ramp_has_the_normal_of_its_slope() {
+        // Sube size.y = 1 a lo largo de size.x = 4 en X: pendiente 1/4
+        let ramp = terrain(6, 5, |u, _| u).expect("la grilla es válida");
+        let expected = Vec3::new(-0.25, 1.0, 0.0).normalize();
+        for ray in rays() {
+            if let Some(hit) = ramp.ray_intersect(&ray) {
+                assert!((hit.normal - expected).magnitude() < 1e-5, "{:?}", hit.normal);
+            }
+        }
+    }
+
+    #[test]
+    fn uvs_stay_in_the_unit_square() {
+        let heightfield = bumpy();
+        for ray in rays() {
+            if let Some(hit) = heightfield.ray_intersect(&ray) {
+                let (u, v) = hit.uv;
+                assert!((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v), "{:?}", hit.uv);
+                // u y v siguen a X y Z dentro de la caja
+                assert!((hit.point.x - (-2.0 + 4.0 * u)).abs() < 1e-4 && (hit.point.z - (-1.5 + 3.0 * v)).abs() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use nalgebra_glm::{Mat4, Vec3};
+use std::rc::Rc;
+
+use crate::bounds::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::ray_intersect::{closest_intersect, Intersect, Interval, RayIntersect, SurfaceSample};
+use crate::sampling::Rng;
+use crate::texture::TextureCoords;
+use crate::transform::Transformed;
+
+// Copia liviana de un prototipo compartido: solo guarda su matriz y, si hace falta, otro material
+pub struct Instance {
+    transformed: Transformed<Rc<dyn RayIntersect>>,
+    material: Option<Rc<Material>>,
+}
+
+impl Instance {
+    // None si la matriz no es invertible
+    pub fn new(prototype: Rc<dyn RayIntersect>, transform: Mat4) -> Option<Self> {
+        Some(Instance {
+            transformed: Transformed::new(prototype, transform)?,
+            material: None,
+        })
+    }
+
+    // Reemplaza el material del prototipo, incluidos su normal map y su relieve
+    pub fn with_material(mut self, material: Rc<Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    // Solo cambia la referencia: texturas, normal map y relieve se evalúan después, en
+    // with_surface_detail y al sombrear, y ya con el material nuevo
+    fn apply_material<'a>(&'a self, intersect: Intersect<'a>) -> Intersect<'a> {
+        match &self.material {
+            Some(material) => Intersect { material, ..intersect },
+            None => intersect,
+        }
+    }
+}
+
+impl RayIntersect for Instance {
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        self.transformed.ray_intersect(ray).map(|intersect| self.apply_material(intersect))
+    }
+
+    fn ray_intervals(&self, ray: &Ray) -> Vec<Interval> {
+        self.transformed.ray_intervals(ray)
+    }
+
+    fn boundary_hit(&self, ray: &Ray, distance: f32) -> Option<Intersect<'_>> {
+        self.transformed.boundary_hit(ray, distance).map(|intersect| self.apply_material(intersect))
+    }
+
+    fn is_emissive(&self) -> bool {
+        match &self.material {
+            Some(material) => material.is_emissive(),
+            None => self.transformed.is_emissive(),
+        }
+    }
+
+    fn surface_area(&self) -> f32 {
+        self.transformed.surface_area()
+    }
+
+    fn sample_surface(&self, rng: &mut Rng) -> Option<SurfaceSample> {
+        let sample = self.transformed.sample_surface(rng)?;
+        match &self.material {
+            Some(material) => {
+                let coords = TextureCoords::new(sample.uv, sample.point, sample.normal, material.texture_mapping);
+                Some(SurfaceSample {
+                    emission: material.emission_at(&coords)?,
+                    ..sample
+                })
+            }
+            None => Some(sample),
+        }
+    }
+
+    fn surface_pdf(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        self.transformed.surface_pdf(point, normal)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.transformed.bounds()
+    }
+}
+
+// Conjunto de objetos con una caja envolvente común: si el rayo no toca la caja no se prueba ningún hijo
+pub struct Group {
+    pub children: Vec<Box<dyn RayIntersect>>,
+    bounds: Option<Aabb>,
+    // Hijos emisivos y su área acumulada, para muestrear el grupo como una sola luz de área
+    emitters: Vec<(usize, f32)>,
+}
+
+impl Group {
+    pub fn new(children: Vec<Box<dyn RayIntersect>>) -> Self {
+        // Si algún hijo no tiene límites el grupo tampoco los tiene
+        let bounds = children
+            .iter()
+            .map(|child| child.bounds())
+            .reduce(|a, b| Some(a?.union(&b?)))
+            .flatten();
+
+        let mut total_area = 0.0;
+        let mut emitters = Vec::new();
+        for (index, child) in children.iter().enumerate() {
+            if child.is_emissive() && child.surface_area() > 0.0 {
+                total_area += child.surface_area();
+                emitters.push((index, total_area));
+            }
+        }
+
+        Group {
+            children,
+            bounds,
+            emitters,
+        }
+    }
+
+    // Área de los hijos que emiten; sample_surface solo elige puntos sobre ellos
+    pub fn emissive_area(&self) -> f32 {
+        self.emitters.last().map_or(0.0, |(_, area)| *area)
+    }
+
+    // Probabilidad de elegir el emisor en esa posición al muestrear el grupo
+    fn child_probability(&self, position: usize, total: f32) -> f32 {
+        let start = if position == 0 { 0.0 } else { self.emitters[position - 1].1 };
+        (self.emitters[position].1 - start) / total
+    }
+}
+
+impl RayIntersect for Group {
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        if self.bounds.is_some_and(|bounds| !bounds.hit(ray)) {
+            return None;
+        }
+        closest_intersect(&self.children, ray).map(|(intersect, _)| intersect)
+    }
+
+    fn is_emissive(&self) -> bool {
+        !self.emitters.is_empty()
+    }
+
+    fn surface_area(&self) -> f32 {
+        self.children.iter().map(|child| child.surface_area()).sum()
+    }
+
+    // Primero un hijo según su área y después un punto sobre él; la densidad queda
+    // multiplicada por la probabilidad de haber elegido ese hijo
+    fn sample_surface(&self, rng: &mut Rng) -> Option<SurfaceSample> {
+        let total = self.emissive_area();
+        let pick = rng.next_f32() * total;
+        let position = self.emitters.iter().position(|(_, area)| pick < *area).unwrap_or(self.emitters.len().checked_sub(1)?);
+        let (index, _) = self.emitters[position];
+        let sample = self.children[index].sample_surface(rng)?;
+        Some(SurfaceSample {
+            pdf: sample.pdf * self.child_probability(position, total),
+            ..sample
+        })
+    }
+
+    // Un punto sobre varios hijos a la vez suma la densidad de cada uno
+    fn surface_pdf(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        let total = self.emissive_area();
+        (0..self.emitters.len())
+            .map(|position| {
+                let (index, _) = self.emitters[position];
+                self.children[index].surface_pdf(point, normal) * self.child_probability(position, total)
+            })
+            .sum()
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::material::MaterialBuilder;
+
+    fn cube_at(x: f32, emissive: bool) -> Box<dyn RayIntersect> {
+        let material = if emissive {
+            MaterialBuilder::emissive(Color::new(1.0, 1.0, 1.0)).build()
+        } else {
+            MaterialBuilder::new().build()
+        };
+        Box::new(Cube {
+            min_corner: Vec3::new(x, 0.0, 0.0),
+            max_corner: Vec3::new(x + 1.0, 1.0, 1.0),
+            material,
+        })
+    }
+
+    #[test]
+    fn group_samples_only_emissive_children() {
+        let group = Group::new(vec![cube_at(0.0, false), cube_at(3.0, true)]);
+        assert_eq!(group.surface_area(), 12.0);
+        assert_eq!(group.emissive_area(), 6.0);
+
+        let mut rng = Rng::new(3);
+        for _ in 0..100 {
+            let sample = group.sample_surface(&mut rng).expect("hay un hijo emisivo");
+            assert!(sample.point.x >= 3.0 - 1e-5);
+            assert!((sample.pdf - 1.0 / 6.0).abs() < 1e-5);
+        }
+        assert_eq!(group.surface_pdf(&Vec3::new(0.5, 1.0, 0.5), &Vec3::new(0.0, 1.0, 0.0)), 0.0);
+    }
+}
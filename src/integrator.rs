@@ -0,0 +1,15 @@
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::scene::Scene;
+
+pub trait Integrator {
+    fn name(&self) -> &str;
+
+    // Los integradores estocásticos se promedian entre cuadros
+    fn is_progressive(&self) -> bool {
+        false
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, rng: &mut Rng) -> Color;
+}
@@ -1,116 +1,178 @@
 mod framebuffer;
+mod ray;
 mod ray_intersect;
 mod cube;
+mod transform;
+mod bounds;
+mod instance;
+mod csg;
+mod sdf;
+mod heightfield;
 mod color;
 mod camera;
 mod light;
 mod material;
+mod pbr;
 mod texture;
+mod assets;
+mod procedural;
+mod environment;
+mod sampling;
+mod path_tracer;
+mod scene;
+mod integrator;
+mod whitted;
+mod debug_view;
+mod ambient_occlusion;
+mod settings;
 
 use minifb::{Window, WindowOptions, Key};
-use nalgebra_glm::{Vec3, normalize};
-use std::time::Duration;
+use nalgebra_glm::{Vec3, normalize, scaling, translation};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use std::f32::consts::PI;
 
-use nalgebra_glm::reflect_vec;
-
 use crate::color::Color;
-use crate::ray_intersect::{Intersect, RayIntersect};
 use crate::cube::Cube;
-use crate::framebuffer::Framebuffer;
+use crate::ray::{Ray, RayDifferentials};
+use crate::ray_intersect::RayIntersect;
+use crate::instance::{Group, Instance};
+use crate::csg::Csg;
+use crate::sdf::{Sdf, SdfObject};
+use crate::heightfield::Heightfield;
+use crate::texture::TextureMapping;
+use crate::bounds::Aabb;
+use crate::framebuffer::{Accumulator, Framebuffer};
 use crate::camera::Camera;
 use crate::light::Light;
-use crate::material::Material;
-use crate::texture::load_texture;
+use crate::material::MaterialBuilder;
+use crate::pbr::PbrMaterial;
+use crate::assets::AssetManager;
+use crate::procedural::{fbm, Marble, TextureSpace};
+use crate::environment::EnvironmentMap;
+use crate::path_tracer::PathTracer;
+use crate::sampling::Rng;
+use crate::scene::Scene;
+use crate::integrator::Integrator;
+use crate::ambient_occlusion::{AmbientOcclusion, AmbientOcclusionIntegrator};
+use crate::debug_view::{
+    AlbedoIntegrator, DepthIntegrator, HeatmapIntegrator, MaterialIdIntegrator, NormalsIntegrator, UvIntegrator,
+};
+use crate::whitted::WhittedIntegrator;
+use crate::settings::RenderSettings;
 
-const ORIGIN_BIAS: f32 = 1e-4;
 const SKYBOX_COLOR: Color = Color::new(0.27, 0.56, 0.89); // Color del cielo (valores entre 0.0 y 1.0)
 
-fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3 {
-    let offset = intersect.normal * ORIGIN_BIAS;
-    if direction.dot(&intersect.normal) < 0.0 {
-        intersect.point - offset
-    } else {
-        intersect.point + offset
-    }
-}
-
-fn cast_ray(
-    ray_origin: &Vec3,
-    ray_direction: &Vec3,
-    objects: &[Cube],
-    lights: &[Light],
-    depth: u32,
-) -> Color {
-    if depth > 3 {
-        return SKYBOX_COLOR;
-    }
+pub fn render(
+    framebuffer: &mut Framebuffer,
+    scene: &Scene,
+    camera: &Camera,
+    integrator: &dyn Integrator,
+    accumulator: &mut Accumulator,
+) {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    let aspect_ratio = width / height;
+    let fov = std::f32::consts::PI / 3.0;
+    let perspective_scale = (fov * 0.5).tan();
 
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY;
+    // Dirección del rayo que pasa por el punto (x, y) de la pantalla, en pixeles
+    let direction_at = |x: f32, y: f32| {
+        let screen_x = (2.0 * x) / width - 1.0;
+        let screen_y = -(2.0 * y) / height + 1.0;
 
-    for object in objects {
-        let i = object.ray_intersect(ray_origin, ray_direction);
-        if i.is_intersecting && i.distance < zbuffer {
-            zbuffer = i.distance;
-            intersect = i;
-        }
-    }
+        let screen_x = screen_x * aspect_ratio * perspective_scale;
+        let screen_y = screen_y * perspective_scale;
 
-    if !intersect.is_intersecting {
-        return SKYBOX_COLOR;
-    }
+        camera.base_change(&normalize(&Vec3::new(screen_x, screen_y, -1.0)))
+    };
 
-    let mut final_color = Color::black();
-
-    final_color = final_color + intersect.material.emission.unwrap_or(Color::black());
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let mut rng = Rng::for_pixel(x, y, accumulator.frames);
 
-    // Procesar cada luz en la escena, si hay alguna
-    for light in lights {
-        let light_dir = (light.position - intersect.point).normalize();
-        let view_dir = (ray_origin - intersect.point).normalize();
+            // Los integradores progresivos mueven el rayo dentro del pixel para suavizar los bordes
+            let (jitter_x, jitter_y) = if integrator.is_progressive() {
+                (rng.next_f32() - 0.5, rng.next_f32() - 0.5)
+            } else {
+                (0.0, 0.0)
+            };
+
+            let (pixel_x, pixel_y) = (x as f32 + jitter_x, y as f32 + jitter_y);
+            // Los vecinos de un pixel de distancia sirven para filtrar el fondo según lo que cubre el pixel
+            let ray = Ray::new(camera.eye, direction_at(pixel_x, pixel_y)).with_differentials(RayDifferentials {
+                x_direction: direction_at(pixel_x + 1.0, pixel_y),
+                y_direction: direction_at(pixel_x, pixel_y + 1.0),
+            });
 
-        let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0).min(1.0);
-        let diffuse = intersect.material.diffuse * intersect.material.albedo[0] * diffuse_intensity * light.intensity;
+            let sample = integrator.radiance(scene, &ray, &mut rng);
+            let pixel_color = if integrator.is_progressive() {
+                // Promedio progresivo de todos los cuadros desde el último cambio
+                accumulator.add(y * framebuffer.width + x, sample)
+            } else {
+                sample
+            };
 
-        let mut reflect_color = Color::black();
-        let reflectivity = intersect.material.albedo[2];
-        if reflectivity > 0.0 {
-            let reflect_dir = reflect_vec(&ray_direction, &intersect.normal).normalize();
-            let reflect_origin = offset_origin(&intersect, &reflect_dir);
-            reflect_color = cast_ray(&reflect_origin, &reflect_dir, objects, lights, depth + 1);
+            framebuffer.set_current_color(pixel_color.to_u32());
+            framebuffer.point(x, y);
         }
-
-        final_color = final_color + diffuse + reflect_color * reflectivity;
     }
 
-    final_color
+    accumulator.frames += 1;
 }
 
-pub fn render(framebuffer: &mut Framebuffer, objects: &[Cube], camera: &Camera, lights: &[Light]) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = std::f32::consts::PI / 3.0;
-    let perspective_scale = (fov * 0.5).tan();
-
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_y = -(2.0 * y as f32) / height + 1.0;
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter().position(|arg| arg == name).and_then(|index| args.get(index + 1))
+}
 
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
+// Valor numérico de una opción; si no se puede leer se muestra el error y se termina el programa
+fn parse_arg<T: std::str::FromStr>(args: &[String], name: &str, expected: &str) -> Option<T> {
+    arg_value(args, name).map(|value| {
+        value.parse().unwrap_or_else(|_| {
+            eprintln!("Valor inválido para {}: '{}' (se esperaba {})", name, value, expected);
+            std::process::exit(2);
+        })
+    })
+}
 
-            let ray_direction = normalize(&Vec3::new(screen_x, screen_y, -1.0));
-            let rotated_direction = camera.base_change(&ray_direction);
+fn mode_name(integrator: &dyn Integrator) -> String {
+    integrator.name().to_lowercase().replace(' ', "-")
+}
 
-            let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, lights, 0);
+// Muestreo de texturas aislado del resto del render: recorrido por filas como una imagen
+// y acceso aleatorio como los rebotes de un path tracer
+fn bench_textures(assets: &AssetManager) {
+    let samples_side = 1024;
+    let mut rng = Rng::new(7);
+    let mut checksum = Color::black();
+    let textures = assets.textures();
+    let total = samples_side * samples_side * textures.len();
+
+    let start = Instant::now();
+    for texture in &textures {
+        for y in 0..samples_side {
+            for x in 0..samples_side {
+                let uv = (x as f32 / samples_side as f32, y as f32 / samples_side as f32);
+                checksum = checksum + texture.evaluate(uv, &Vec3::zeros());
+            }
+        }
+    }
+    let coherent = start.elapsed().as_secs_f32() * 1.0e9 / total as f32;
 
-            framebuffer.set_current_color(pixel_color.to_u32());
-            framebuffer.point(x, y);
+    let start = Instant::now();
+    for texture in &textures {
+        for _ in 0..samples_side * samples_side {
+            checksum = checksum + texture.evaluate((rng.next_f32(), rng.next_f32()), &Vec3::zeros());
         }
     }
+    let random = start.elapsed().as_secs_f32() * 1.0e9 / total as f32;
+
+    println!(
+        "Texturas: {:.1} ns por muestra en orden, {:.1} ns al azar (suma {:.0})",
+        coherent,
+        random,
+        checksum.r + checksum.g + checksum.b
+    );
 }
 
 fn main() {
@@ -122,81 +184,89 @@ fn main() {
 
     let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
 
-    let mut window = Window::new(
-        "Refractor",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    ).unwrap();
+    let args: Vec<String> = std::env::args().collect();
+
+    // --output <archivo.png> renderiza una sola imagen en calidad offline, sin abrir la ventana
+    let output_path = arg_value(&args, "--output");
+    let settings = if output_path.is_some() {
+        RenderSettings::offline()
+    } else {
+        RenderSettings::interactive()
+    };
+
+    let mut assets = AssetManager::new();
 
     // Material con textura para todas las caras del cubo
-    let textured_material = Material::new(
-        Color::new(1.0, 1.0, 1.0),  // Color base en formato f32
-        50.0,
-        [0.6, 0.3, 0.0, 0.0],
-        1.0,
-        Some(load_texture("./texture/grass.png")),
-        None,
-        None,
-    );
+    let textured_material = MaterialBuilder::new()
+        .texture(assets.texture("./texture/grass.png"))
+        .build();
 
     // Material con textura de agua para los cubos del centro
-    let water_material = Material::new(
-        Color::new(1.0, 1.0, 1.0),
-        50.0,
-        [0.6, 0.3, 0.5, 0.0],  // Aumentamos el valor de reflectividad a 0.5
-        1.0,
-        Some(load_texture("./texture/water.jpeg")),
-        None,
-        None,
-    );
-
-    // Material para la mini torre con textura de calabaza (pumpkin) que emite una luz más suave
-    let pumpkin = Material::new(
-        Color::new(1.0, 1.0, 1.0),  // Color base, pero la textura lo sobreescribe
-        50.0,  // Valor especular
-        [0.6, 0.3, 0.0, 0.0],  // Albedo
-        1.0,  // Índice de refracción
-        Some(load_texture("./texture/jack.jpeg")), 
-        None,  
-        Some(Color::new(0.2, 0.1, 0.0)),  // Emisión más suave con un tono anaranjado
-    );
+    let water_material = MaterialBuilder::water()
+        .texture(assets.texture("./texture/water.jpeg"))
+        .build();
+
+    // Material para la mini torre con textura de calabaza (pumpkin); solo la cara tallada brilla
+    let jack_texture = assets.texture("./texture/jack.jpeg");
+    let pumpkin = MaterialBuilder::emissive(Color::new(1.0, 0.6, 0.2))  // Tono anaranjado para la luz
+        .texture(jack_texture.clone())
+        .emission_map(jack_texture.clone())
+        .emission_strength(0.8)
+        .emission_threshold(0.6)
+        .build();
 
     // Material para la mini torre con textura de cobblestone
-    let tower_material = Material::new(
-        Color::new(1.0, 1.0, 1.0),  // Color base, pero la textura lo sobreescribe
-        50.0,  // Valor especular
-        [0.6, 0.3, 0.0, 0.0],  // Albedo
-        1.0,  // Índice de refracción
-        Some(load_texture("./texture/cobble.png")),  
-        None,  
-        None,  
-    );
-
-    let mut objects = Vec::new();
+    let cobble_texture = assets.texture("./texture/cobble.png");
+    let tower_material = MaterialBuilder::new()
+        .texture(cobble_texture.clone())
+        // Las piedras claras sobresalen de las juntas oscuras
+        .height_map(cobble_texture.clone())
+        .height_scale(0.04)
+        .parallax_shadows(true)
+        .pbr(PbrMaterial {
+            // Piedra rugosa; la misma textura oscurece las juntas como mapa de oclusión
+            base_color_map: Some(cobble_texture.clone()),
+            roughness_map: Some(cobble_texture.clone()),
+            ao_map: Some(cobble_texture.clone()),
+            ..PbrMaterial::new(Color::new(0.6, 0.6, 0.6), 0.0, 0.9)
+        })
+        .build();
+
+    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
     let cube_size = 0.5;
     let low_cube_height = 0.25;
     let grid_size = 5;
 
+    // El piso usa un solo cubo unitario compartido; cada bloque es una instancia con su matriz
+    let grass_block: Rc<dyn RayIntersect> = Rc::new(Cube {
+        min_corner: Vec3::new(-0.5, -0.5, -0.5),
+        max_corner: Vec3::new(0.5, 0.5, 0.5),
+        material: textured_material.clone(),
+    });
+    let water = Rc::new(water_material.clone());
+    let block = |min_corner: Vec3, max_corner: Vec3| {
+        Instance::new(grass_block.clone(), translation(&((min_corner + max_corner) * 0.5)) * scaling(&(max_corner - min_corner)))
+            .expect("Los bloques del piso tienen tamaño en los tres ejes")
+    };
+    let mut floor: Vec<Box<dyn RayIntersect>> = Vec::new();
+
     for i in 0..grid_size {
         for j in 0..grid_size {
             let x_pos = i as f32 * cube_size - (grid_size as f32 * cube_size) / 2.0;
             let z_pos = j as f32 * cube_size - (grid_size as f32 * cube_size) / 2.0;
 
             // Cubos centrales más bajos (agua, que reflejarán)
-            if i >= 2 && i <= 3 && j >= 2 && j <= 3 {
-                objects.push(Cube {
-                    min_corner: Vec3::new(x_pos, 0.0, z_pos),
-                    max_corner: Vec3::new(x_pos + cube_size, low_cube_height, z_pos + cube_size),
-                    material: water_material.clone(),  // Usar el material de agua
-                });
+            if (2..=3).contains(&i) && (2..=3).contains(&j) {
+                floor.push(Box::new(
+                    block(Vec3::new(x_pos, 0.0, z_pos), Vec3::new(x_pos + cube_size, low_cube_height, z_pos + cube_size))
+                        .with_material(water.clone()),  // Usar el material de agua
+                ));
             } else {
                 // Otros cubos normales
-                objects.push(Cube {
-                    min_corner: Vec3::new(x_pos, 0.0, z_pos),
-                    max_corner: Vec3::new(x_pos + cube_size, cube_size, z_pos + cube_size),
-                    material: textured_material.clone(),
-                });
+                floor.push(Box::new(block(
+                    Vec3::new(x_pos, 0.0, z_pos),
+                    Vec3::new(x_pos + cube_size, cube_size, z_pos + cube_size),
+                )));
             }
         }
     }
@@ -206,48 +276,112 @@ fn main() {
             let x_pos = i as f32 * cube_size - (grid_size as f32 * cube_size) / 2.0;
             let z_pos = j as f32 * cube_size - (grid_size as f32 * cube_size) / 2.0;
 
-            objects.push(Cube {
-                min_corner: Vec3::new(x_pos, low_cube_height, z_pos),  // Altura más baja
-                max_corner: Vec3::new(x_pos + cube_size, cube_size - 0.03, z_pos + cube_size),  // Más pequeños
-                material: water_material.clone(),  // Usar el material de agua con reflejos
-            });
+            floor.push(Box::new(
+                block(
+                    Vec3::new(x_pos, low_cube_height, z_pos),  // Altura más baja
+                    Vec3::new(x_pos + cube_size, cube_size - 0.03, z_pos + cube_size),  // Más pequeños
+                )
+                .with_material(water.clone()),  // Usar el material de agua con reflejos
+            ));
         }
     }
+    objects.push(Box::new(Group::new(floor)));
 
     let x_pos = 0.0 * cube_size - (grid_size as f32 * cube_size) / 2.0;
     let z_pos = 0.0 * cube_size - (grid_size as f32 * cube_size) / 2.0;
 
-    objects.push(Cube {
+    objects.push(Box::new(Cube {
         min_corner: Vec3::new(x_pos, 0.0, z_pos),  // Al nivel del piso
         max_corner: Vec3::new(x_pos + cube_size, cube_size, z_pos + cube_size),  // Mismo tamaño que los bloques del piso
         material: tower_material.clone(),  // Usar la textura de piedra
-    });
-
-    objects.push(Cube {
-        min_corner: Vec3::new(x_pos, cube_size, z_pos),  // Encima del primer bloque
-        max_corner: Vec3::new(x_pos + cube_size, cube_size * 2.0, z_pos + cube_size),
-        material: tower_material.clone(),  // Usar la textura de piedra
-    });
-
-    objects.push(Cube {
+    }));
+
+    // Ventana en cruz tallada de lado a lado en el segundo bloque
+    let window_center = Vec3::new(x_pos + cube_size * 0.5, cube_size * 1.5, z_pos + cube_size * 0.5);
+    let window_slot = |half_size: Vec3| -> Box<dyn RayIntersect> {
+        Box::new(Cube {
+            min_corner: window_center - half_size,
+            max_corner: window_center + half_size,
+            material: tower_material.clone(),
+        })
+    };
+    let window = Csg::union(
+        window_slot(Vec3::new(0.04, 0.15, cube_size)),
+        window_slot(Vec3::new(0.15, 0.04, cube_size)),
+    );
+    objects.push(Box::new(Csg::difference(
+        Box::new(Cube {
+            min_corner: Vec3::new(x_pos, cube_size, z_pos),  // Encima del primer bloque
+            max_corner: Vec3::new(x_pos + cube_size, cube_size * 2.0, z_pos + cube_size),
+            material: tower_material.clone(),  // Usar la textura de piedra
+        }),
+        Box::new(window),
+    )));
+
+    objects.push(Box::new(Cube {
         min_corner: Vec3::new(x_pos, cube_size * 2.0, z_pos),  // Encima del segundo bloque
         max_corner: Vec3::new(x_pos + cube_size, cube_size * 3.0, z_pos + cube_size),
         material: tower_material.clone(),  // Usar la textura de piedra
-    });
+    }));
 
-    objects.push(Cube {
+    objects.push(Box::new(Cube {
         min_corner: Vec3::new(x_pos, cube_size * 3.0, z_pos),  // Encima del tercer bloque
         max_corner: Vec3::new(x_pos + cube_size, cube_size * 4.0, z_pos + cube_size),
         material: pumpkin.clone(),  // Usar la textura de piedra
-    });
+    }));
 
     let pumpkin_x = 1.0 * cube_size - (grid_size as f32 * cube_size) / 2.0;
     let pumpkin_z = 1.0 * cube_size - (grid_size as f32 * cube_size) / 2.0;
-    objects.push(Cube {
-        min_corner: Vec3::new(pumpkin_x, 0.0, pumpkin_z),  
-        max_corner: Vec3::new(pumpkin_x + cube_size, cube_size, pumpkin_z + cube_size),  
-        material: pumpkin.clone(),  
-    });
+    objects.push(Box::new(Cube {
+        min_corner: Vec3::new(pumpkin_x, 0.0, pumpkin_z),
+        max_corner: Vec3::new(pumpkin_x + cube_size, cube_size, pumpkin_z + cube_size),
+        material: pumpkin.clone(),
+    }));
+
+    // Farol de mármol modelado con distancias: base redondeada con ranura, poste, esfera y anillo
+    let lamp_material = MaterialBuilder::new()
+        .texture(Rc::new(Marble::new(Color::new(0.9, 0.88, 0.85), Color::new(0.35, 0.35, 0.4), 6.0, TextureSpace::World)))
+        .specular(80.0)
+        .reflectivity(0.1)
+        .build();
+    let lamp_base = Sdf::RoundBox { half_size: Vec3::new(0.18, 0.05, 0.18), radius: 0.03 }
+        .translate(Vec3::new(0.0, 0.05, 0.0))
+        .smooth_subtract(Sdf::Box { half_size: Vec3::new(0.2, 0.015, 0.02) }.translate(Vec3::new(0.0, 0.05, 0.0)), 0.01);
+    // Grilla de 3x3 perlas sobre la base
+    let lamp_beads = Sdf::Sphere { radius: 0.025 }
+        .repeat(Vec3::new(0.12, 1.0, 0.12), Vec3::new(1.0, 0.0, 1.0))
+        .translate(Vec3::new(0.0, 0.1, 0.0));
+    let lamp_pole = Sdf::Capsule { a: Vec3::new(0.0, 0.08, 0.0), b: Vec3::new(0.0, 0.35, 0.0), radius: 0.035 };
+    let lamp_top = Sdf::Sphere { radius: 0.08 }
+        .smooth_union(Sdf::Torus { major_radius: 0.11, minor_radius: 0.015 }, 0.02)
+        .translate(Vec3::new(0.0, 0.4, 0.0));
+    let lamp_shape = lamp_base
+        .smooth_union(lamp_beads, 0.02)
+        .smooth_union(lamp_pole, 0.05)
+        .smooth_union(lamp_top, 0.04);
+    let lamp_position = Vec3::new(4.5 * cube_size - (grid_size as f32 * cube_size) / 2.0, cube_size, 0.5 * cube_size - (grid_size as f32 * cube_size) / 2.0);
+    objects.push(Box::new(SdfObject::new(
+        lamp_shape.translate(lamp_position),
+        Aabb::new(lamp_position + Vec3::new(-0.22, -0.01, -0.22), lamp_position + Vec3::new(0.22, 0.52, 0.22)),
+        lamp_material,
+    )));
+
+    // Lomas de césped alrededor de la isla; el borde de los bloques queda semienterrado
+    let terrain_material = MaterialBuilder::new()
+        .texture(assets.texture("./texture/grass.png"))
+        .texture_mapping(TextureMapping::Triplanar { scale: 2.0, sharpness: 4.0 })
+        .build();
+    match Heightfield::from_fn(
+        96,
+        96,
+        Vec3::new(-5.0, -0.35, -6.0),
+        Vec3::new(10.0, 0.7, 9.0),
+        terrain_material,
+        |u, v| fbm(&Vec3::new(u * 3.0, v * 2.7, 0.5), 4) + 0.5,
+    ) {
+        Ok(terrain) => objects.push(Box::new(terrain)),
+        Err(e) => eprintln!("No se pudo crear el terreno: {}", e),
+    }
 
     let mut camera = Camera::new(
         Vec3::new(0.0, 1.5, 3.0),
@@ -267,13 +401,120 @@ fn main() {
         1.5,  
     );
 
-    let mut lights = vec![light1.clone(), light2.clone()];
+    // --environment <imagen> usa un panorama equirectangular como cielo; sin él, un degradado procedural
+    let sky_gradient = || EnvironmentMap::gradient(SKYBOX_COLOR, Color::new(0.7, 0.8, 0.95), Color::new(0.3, 0.27, 0.22));
+    let environment = match arg_value(&args, "--environment") {
+        Some(path) => EnvironmentMap::from_image(path).unwrap_or_else(|e| {
+            eprintln!("Error al cargar el mapa de entorno desde {}: {}", path, e);
+            sky_gradient()
+        }),
+        None => sky_gradient(),
+    }
+    .with_intensity(0.6);
+
+    println!("Texturas cargadas: {} ({:.1} MB)", assets.texture_count(), assets.memory_bytes() as f32 / (1024.0 * 1024.0));
+
+    let mut scene = Scene::new(objects, vec![light1.clone(), light2.clone()], environment);
     let mut lights_on = true;  
 
     let rotation_speed = PI / 10.0;
     let move_speed = 0.1;
 
+    // --ao-samples y --ao-radius configuran la oclusión ambiental; en Whitted solo se activa si se pide
+    let ao_samples: Option<u32> = parse_arg(&args, "--ao-samples", "un entero");
+    let ao_radius = parse_arg(&args, "--ao-radius", "un número").unwrap_or(0.5);
+    let ambient_occlusion = AmbientOcclusion::new(ao_samples.unwrap_or(4), ao_radius);
+    let mut whitted = WhittedIntegrator::new(3, &settings);
+    if ao_samples.is_some_and(|samples| samples > 0) {
+        whitted = whitted.with_ambient_occlusion(ambient_occlusion);
+    }
+
+    let integrators: Vec<Box<dyn Integrator>> = vec![
+        Box::new(whitted),
+        Box::new(PathTracer::new(8, 3)),
+        Box::new(AmbientOcclusionIntegrator::new(ambient_occlusion)),
+        Box::new(NormalsIntegrator),
+        Box::new(DepthIntegrator::new(10.0)),
+        Box::new(UvIntegrator),
+        Box::new(MaterialIdIntegrator),
+        Box::new(AlbedoIntegrator),
+        // Rojo a partir de 300 pruebas de cajas y primitivas por pixel
+        Box::new(HeatmapIntegrator::new(Box::new(WhittedIntegrator::new(3, &settings)), 300)),
+    ];
+    let integrator_keys = [
+        Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    ];
+    let mut current_integrator = 0;
+
+    // --mode <nombre> elige el integrador inicial, por ejemplo --mode normals o --mode material-id
+    if let Some(mode) = arg_value(&args, "--mode") {
+        match integrators.iter().position(|integrator| mode_name(integrator.as_ref()) == mode.to_lowercase()) {
+            Some(index) => current_integrator = index,
+            None => {
+                let names: Vec<String> = integrators.iter().map(|integrator| mode_name(integrator.as_ref())).collect();
+                eprintln!("Modo desconocido '{}', opciones: {}", mode, names.join(", "));
+            }
+        }
+    }
+    let mut accumulator = Accumulator::new(framebuffer_width, framebuffer_height);
+
+    // --bench <cuadros> mide el tiempo de render del integrador elegido, sin ventana
+    if let Some(frames) = parse_arg::<u32>(&args, "--bench", "un entero") {
+        let integrator = integrators[current_integrator].as_ref();
+
+        // Un cuadro de calentamiento fuera de la medición
+        render(&mut framebuffer, &scene, &camera, integrator, &mut accumulator);
+        let start = Instant::now();
+        for _ in 0..frames {
+            render(&mut framebuffer, &scene, &camera, integrator, &mut accumulator);
+        }
+        let elapsed = start.elapsed().as_secs_f32() * 1000.0;
+        println!(
+            "{}: {} cuadros en {:.1} ms ({:.2} ms por cuadro)",
+            integrator.name(),
+            frames,
+            elapsed,
+            elapsed / frames.max(1) as f32
+        );
+
+        bench_textures(&assets);
+        return;
+    }
+
+    if let Some(output_path) = output_path {
+        let integrator = integrators[current_integrator].as_ref();
+        // --samples cambia la cantidad de cuadros acumulados
+        let frames = if integrator.is_progressive() {
+            parse_arg(&args, "--samples", "un entero").unwrap_or(settings.frames)
+        } else {
+            1
+        };
+
+        for _ in 0..frames {
+            render(&mut framebuffer, &scene, &camera, integrator, &mut accumulator);
+        }
+
+        match framebuffer.save(output_path) {
+            Ok(()) => println!("Imagen guardada en {} ({} cuadros)", output_path, frames),
+            Err(e) => eprintln!("Error al guardar la imagen en {}: {}", output_path, e),
+        }
+        return;
+    }
+
+    let mut window = Window::new(
+        "Refractor",
+        window_width,
+        window_height,
+        WindowOptions::default(),
+    ).unwrap();
+    window.set_title(&format!("Refractor - {}", integrators[current_integrator].name()));
+
+    let asset_poll_interval = Duration::from_millis(500);
+    let mut last_asset_poll = Instant::now();
+
     while window.is_open() && !window.is_key_down(Key::Escape) {
+        let mut scene_changed = !window.get_keys().is_empty();
+
         if window.is_key_down(Key::Left) {
             camera.orbit(rotation_speed, 0.0);
         }
@@ -303,14 +544,46 @@ fn main() {
         if window.is_key_pressed(Key::R, minifb::KeyRepeat::No) {
             lights_on = !lights_on;
             if lights_on {
-                lights = vec![light1.clone(), light2.clone()];  
+                scene.lights = vec![light1.clone(), light2.clone()];  
             } else {
-                lights.clear();  
+                scene.lights.clear();  
             }
         }
-        
 
-        render(&mut framebuffer, &objects, &camera, &lights);
+        // F5 vuelve a leer las texturas desde disco
+        if window.is_key_pressed(Key::F5, minifb::KeyRepeat::No) {
+            for (path, e) in assets.reload_all() {
+                eprintln!("Error al recargar la textura {}: {}", path, e);
+            }
+            scene_changed = true;
+        }
+
+        // Cada tanto se revisa si algún archivo de textura cambió y se recarga en la escena abierta
+        if last_asset_poll.elapsed() >= asset_poll_interval {
+            last_asset_poll = Instant::now();
+            for (path, result) in assets.reload_changed() {
+                match result {
+                    Ok(()) => println!("Textura recargada: {}", path),
+                    Err(e) => eprintln!("Error al recargar la textura {}: {}", path, e),
+                }
+                scene_changed = true;
+            }
+        }
+
+        // Teclas 1-9 para cambiar de integrador o vista de depuración
+        for (index, key) in integrator_keys.iter().enumerate() {
+            if index < integrators.len() && window.is_key_pressed(*key, minifb::KeyRepeat::No) && index != current_integrator {
+                current_integrator = index;
+                window.set_title(&format!("Refractor - {}", integrators[current_integrator].name()));
+                scene_changed = true;
+            }
+        }
+
+        if scene_changed {
+            accumulator.reset();
+        }
+
+        render(&mut framebuffer, &scene, &camera, integrators[current_integrator].as_ref(), &mut accumulator);
         window.update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height).unwrap();
         std::thread::sleep(frame_delay);
     }
@@ -1,48 +1,438 @@
-use crate::texture::Texture;
+use crate::texture::{Texture, TextureCoords, TextureMapping};
+use nalgebra_glm::Vec3;
 use std::rc::Rc;
 use crate::color::Color;
+use crate::pbr::{PbrMaterial, PbrSurface};
+
+// Sentido del canal verde del normal map: OpenGL (Y+) apunta hacia arriba en la imagen, DirectX (Y-) hacia abajo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalMapConvention {
+    OpenGl,
+    DirectX,
+}
 
 #[derive(Debug, Clone)]
 pub struct Material {
     pub diffuse: Color,
-    pub specular: f32,
-    pub albedo: [f32; 4],
+    pub specular: f32,  // Exponente especular (brillo)
+    pub diffuse_weight: f32,
+    pub specular_weight: f32,
+    pub reflectivity: f32,
+    pub roughness: f32,  // 0 = espejo perfecto, valores mayores difuminan el reflejo
+    pub transmission: f32,
     pub refractive_index: f32,
-    pub texture: Option<Rc<Texture>>,  // Textura opcional
-    pub normal_map: Option<Rc<Texture>>,  // Normal map opcional
+    pub texture: Option<Rc<dyn Texture>>,  // Textura opcional
+    pub texture_mapping: TextureMapping,  // Proyección de las texturas de color; normal y height map siguen en UV
+    pub normal_map: Option<Rc<dyn Texture>>,  // Normal map opcional
+    pub normal_map_convention: NormalMapConvention,
+    pub height_map: Option<Rc<dyn Texture>>,  // Canal rojo, blanco = alto
+    pub height_scale: f32,  // Profundidad máxima del relieve en unidades de UV
+    pub parallax_shadows: bool,
     pub emission: Option<Color>,
+    pub emission_map: Option<Rc<dyn Texture>>,  // Mapa de emisión con las mismas UV que la textura
+    pub emission_strength: f32,
+    pub emission_threshold: f32,  // Luminancia mínima del mapa para emitir
+    pub pbr: Option<PbrMaterial>,  // Modelo PBR explícito; si falta se convierte desde los parámetros viejos
 }
 
 impl Material {
+    // albedo = [difuso, especular, reflexión, transmisión]
     pub fn new(
         diffuse: Color,
         specular: f32,
         albedo: [f32; 4],
         refractive_index: f32,
-        texture: Option<Rc<Texture>>,  // Soporte para texturas
-        normal_map: Option<Rc<Texture>>,  // Soporte para normal maps
+        texture: Option<Rc<dyn Texture>>,  // Soporte para texturas
+        normal_map: Option<Rc<dyn Texture>>,  // Soporte para normal maps
         emission: Option<Color>,  // Color de emisión (para objetos que emiten luz)
     ) -> Self {
         Material {
             diffuse,
             specular,
-            albedo,
+            diffuse_weight: albedo[0],
+            specular_weight: albedo[1],
+            reflectivity: albedo[2],
+            roughness: 0.0,
+            transmission: albedo[3],
             refractive_index,
             texture,
+            texture_mapping: TextureMapping::Uv,
             normal_map,
+            normal_map_convention: NormalMapConvention::OpenGl,
+            height_map: None,
+            height_scale: 0.05,
+            parallax_shadows: false,
             emission,
+            emission_map: None,
+            emission_strength: 1.0,
+            emission_threshold: 0.0,
+            pbr: None,
         }
     }
 
-    pub fn black() -> Self {
-        Material {
-            diffuse: Color::black(),  // Usar el nuevo método black() que devuelve f32
-            specular: 0.0,
-            albedo: [0.0, 0.0, 0.0, 0.0],
-            refractive_index: 0.0,
-            texture: None,
-            normal_map: None,
-            emission: None,
+    pub fn is_emissive(&self) -> bool {
+        self.emission.is_some() || self.emission_map.is_some()
+    }
+
+    // Emisión en (u, v): el color de emisión se multiplica por el mapa si hay uno
+    pub fn emission_at(&self, coords: &TextureCoords) -> Option<Color> {
+        let tint = self.emission.unwrap_or(Color::new(1.0, 1.0, 1.0));
+        match &self.emission_map {
+            Some(map) => {
+                let texel = coords.sample(map.as_ref());
+                let luminance = 0.2126 * texel.r + 0.7152 * texel.g + 0.0722 * texel.b;
+                // Rampa suave para que solo los texeles claros brillen
+                let mask = ((luminance - self.emission_threshold) / (1.0 - self.emission_threshold).max(1e-4)).clamp(0.0, 1.0);
+                Some(tint * texel * (mask * self.emission_strength))
+            }
+            None => self.emission.map(|emission| emission * self.emission_strength),
+        }
+    }
+
+    // Color de la textura en el punto; sin textura se usa el color base
+    pub fn diffuse_at(&self, coords: &TextureCoords) -> Color {
+        match &self.texture {
+            Some(texture) => coords.sample(texture.as_ref()),
+            None => self.diffuse,
+        }
+    }
+
+    // Normal del normal map en uv pasada a mundo; tangent y bitangent siguen a u y v
+    pub fn perturb_normal(&self, normal: &Vec3, tangent: &Vec3, bitangent: &Vec3, uv: (f32, f32), point: &Vec3) -> Vec3 {
+        let Some(normal_map) = &self.normal_map else {
+            return *normal;
+        };
+        let pixel = normal_map.evaluate(uv, point);
+
+        // v crece hacia abajo en la imagen, así que el verde de OpenGL va contra la bitangente
+        let green = pixel.g * 2.0 - 1.0;
+        let green = match self.normal_map_convention {
+            NormalMapConvention::OpenGl => -green,
+            NormalMapConvention::DirectX => green,
+        };
+        let normal_tangent = Vec3::new(pixel.r * 2.0 - 1.0, green, pixel.b * 2.0 - 1.0);
+
+        (tangent * normal_tangent.x + bitangent * normal_tangent.y + normal * normal_tangent.z).normalize()
+    }
+
+    // Parallax occlusion mapping: avanza por capas en la dirección de la vista hasta chocar con el relieve.
+    // view está en espacio tangente y apunta hacia la cámara
+    pub fn parallax_uv(&self, uv: (f32, f32), point: &Vec3, view: &Vec3) -> (f32, f32) {
+        let Some(height_map) = &self.height_map else {
+            return uv;
+        };
+        if view.z <= 1e-4 || self.height_scale <= 0.0 {
+            return uv;
         }
+
+        // Más capas cuando la vista es rasante
+        let layers = 8.0 + 24.0 * (1.0 - view.z).clamp(0.0, 1.0);
+        let layer_depth = 1.0 / layers;
+        let step_u = view.x / view.z * self.height_scale / layers;
+        let step_v = view.y / view.z * self.height_scale / layers;
+
+        let (mut u, mut v) = uv;
+        let mut current_depth = 0.0;
+        let mut surface_depth = relief_depth(height_map.as_ref(), (u, v), point);
+        let mut previous = (u, v, surface_depth - current_depth);
+
+        while current_depth < surface_depth && current_depth < 1.0 {
+            previous = (u, v, surface_depth - current_depth);
+            u -= step_u;
+            v -= step_v;
+            current_depth += layer_depth;
+            surface_depth = relief_depth(height_map.as_ref(), (u, v), point);
+        }
+
+        // Interpolar entre la última capa sobre el relieve y la primera debajo
+        let after = surface_depth - current_depth;
+        let before = previous.2;
+        let weight = if (after - before).abs() > 1e-6 { after / (after - before) } else { 0.0 };
+        let u = previous.0 * weight + u * (1.0 - weight);
+        let v = previous.1 * weight + v * (1.0 - weight);
+        (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
+    }
+
+    // Sombra suave del relieve sobre sí mismo; light está en espacio tangente
+    pub fn parallax_shadow(&self, uv: (f32, f32), point: &Vec3, light: &Vec3) -> f32 {
+        let Some(height_map) = &self.height_map else {
+            return 1.0;
+        };
+        if light.z <= 1e-4 || self.height_scale <= 0.0 {
+            return 1.0;
+        }
+
+        let (mut u, mut v) = uv;
+        let start_depth = relief_depth(height_map.as_ref(), (u, v), point);
+        if start_depth <= 0.0 {
+            return 1.0;
+        }
+
+        let layers = 8.0 + 24.0 * (1.0 - light.z).clamp(0.0, 1.0);
+        let layer_depth = start_depth / layers;
+        let step_u = light.x / light.z * self.height_scale * start_depth / layers;
+        let step_v = light.y / light.z * self.height_scale * start_depth / layers;
+
+        // El bloqueo que más sobresale, atenuado con la distancia al punto
+        let mut shadow: f32 = 0.0;
+        let mut current_depth = start_depth - layer_depth;
+        let mut step = 1.0;
+        while current_depth > 0.0 {
+            u += step_u;
+            v += step_v;
+            let blocking = current_depth - relief_depth(height_map.as_ref(), (u, v), point);
+            if blocking > 0.0 {
+                shadow = shadow.max(blocking * (1.0 - step / layers) * 8.0);
+            }
+            current_depth -= layer_depth;
+            step += 1.0;
+        }
+
+        1.0 - shadow.clamp(0.0, 1.0)
+    }
+
+    // Parámetros PBR en el punto (u, v) de la superficie. Si el Material emite, su emisión manda
+    // también con un PBR explícito: es la misma que muestrean las luces de área, así MIS queda parejo
+    pub fn pbr_surface(&self, coords: &TextureCoords) -> PbrSurface {
+        let surface = match &self.pbr {
+            Some(pbr) => pbr.surface(coords),
+            None => PbrMaterial::from(self).surface(coords),
+        };
+        match self.emission_at(coords) {
+            Some(emission) => PbrSurface { emission, ..surface },
+            None => surface,
+        }
+    }
+}
+
+pub struct MaterialBuilder {
+    material: Material,
+}
+
+impl Default for MaterialBuilder {
+    fn default() -> Self {
+        MaterialBuilder::new()
+    }
+}
+
+impl MaterialBuilder {
+    pub fn new() -> Self {
+        MaterialBuilder {
+            // Gris como el color que tenían los cubos sin textura
+            material: Material::new(
+                Color::new(0.5, 0.5, 0.5),
+                50.0,
+                [0.6, 0.3, 0.0, 0.0],
+                1.0,
+                None,
+                None,
+                None,
+            ),
+        }
+    }
+
+    pub fn water() -> Self {
+        MaterialBuilder::new()
+            .reflectivity(0.5)
+            .roughness(0.08)
+            .refractive_index(1.33)
+    }
+
+    pub fn emissive(emission: Color) -> Self {
+        MaterialBuilder::new().emission(emission)
+    }
+
+    pub fn refractive_index(mut self, refractive_index: f32) -> Self {
+        self.material.refractive_index = refractive_index;
+        self
+    }
+
+    pub fn specular(mut self, exponent: f32) -> Self {
+        self.material.specular = exponent;
+        self
+    }
+
+    pub fn reflectivity(mut self, weight: f32) -> Self {
+        self.material.reflectivity = weight;
+        self
+    }
+
+    pub fn roughness(mut self, roughness: f32) -> Self {
+        self.material.roughness = roughness;
+        self
+    }
+
+    pub fn texture(mut self, texture: Rc<dyn Texture>) -> Self {
+        self.material.texture = Some(texture);
+        self
+    }
+
+    pub fn texture_mapping(mut self, mapping: TextureMapping) -> Self {
+        self.material.texture_mapping = mapping;
+        self
+    }
+
+    pub fn height_map(mut self, height_map: Rc<dyn Texture>) -> Self {
+        self.material.height_map = Some(height_map);
+        self
+    }
+
+    pub fn height_scale(mut self, scale: f32) -> Self {
+        self.material.height_scale = scale;
+        self
+    }
+
+    pub fn parallax_shadows(mut self, enabled: bool) -> Self {
+        self.material.parallax_shadows = enabled;
+        self
+    }
+
+    pub fn emission(mut self, emission: Color) -> Self {
+        self.material.emission = Some(emission);
+        self
+    }
+
+    pub fn emission_map(mut self, emission_map: Rc<dyn Texture>) -> Self {
+        self.material.emission_map = Some(emission_map);
+        self
+    }
+
+    pub fn emission_strength(mut self, strength: f32) -> Self {
+        self.material.emission_strength = strength;
+        self
+    }
+
+    pub fn emission_threshold(mut self, threshold: f32) -> Self {
+        self.material.emission_threshold = threshold;
+        self
+    }
+
+    pub fn pbr(mut self, pbr: PbrMaterial) -> Self {
+        self.material.pbr = Some(pbr);
+        self
+    }
+
+    pub fn build(self) -> Material {
+        self.material
+    }
+}
+
+// Presets y pesos que la escena de ejemplo no usa; los valores se revisan en las pruebas del final
+#[allow(dead_code)]
+impl MaterialBuilder {
+    pub fn matte(color: Color) -> Self {
+        MaterialBuilder::new()
+            .diffuse(color)
+            .specular(10.0)
+            .diffuse_weight(0.9)
+            .specular_weight(0.1)
+    }
+
+    pub fn mirror() -> Self {
+        MaterialBuilder::new()
+            .specular(1500.0)
+            .diffuse_weight(0.0)
+            .specular_weight(0.8)
+            .reflectivity(0.9)
+    }
+
+    // Whitted no refracta: la transmisión solo la sigue el path tracer
+    pub fn glass() -> Self {
+        MaterialBuilder::new()
+            .specular(125.0)
+            .diffuse_weight(0.0)
+            .specular_weight(0.5)
+            .reflectivity(0.1)
+            .transmission(0.9)
+            .refractive_index(1.5)
+    }
+
+    pub fn diffuse(mut self, color: Color) -> Self {
+        self.material.diffuse = color;
+        self
+    }
+
+    pub fn diffuse_weight(mut self, weight: f32) -> Self {
+        self.material.diffuse_weight = weight;
+        self
+    }
+
+    pub fn specular_weight(mut self, weight: f32) -> Self {
+        self.material.specular_weight = weight;
+        self
+    }
+
+    pub fn transmission(mut self, weight: f32) -> Self {
+        self.material.transmission = weight;
+        self
+    }
+
+    pub fn normal_map(mut self, normal_map: Rc<dyn Texture>) -> Self {
+        self.material.normal_map = Some(normal_map);
+        self
+    }
+
+    pub fn normal_map_convention(mut self, convention: NormalMapConvention) -> Self {
+        self.material.normal_map_convention = convention;
+        self
+    }
+}
+
+// Profundidad del relieve en (u, v): 0 en la superficie, 1 en el fondo
+fn relief_depth(height_map: &dyn Texture, uv: (f32, f32), point: &Vec3) -> f32 {
+    1.0 - height_map.evaluate(uv, point).r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct FlatTexture(Color);
+
+    impl Texture for FlatTexture {
+        fn evaluate(&self, _uv: (f32, f32), _point: &Vec3) -> Color {
+            self.0
+        }
+    }
+
+    // Normal map con el verde al máximo, en un marco tangente alineado con los ejes
+    fn perturbed(convention: NormalMapConvention) -> Vec3 {
+        let material = MaterialBuilder::new()
+            .normal_map(Rc::new(FlatTexture(Color::new(0.5, 1.0, 0.5))))
+            .normal_map_convention(convention)
+            .build();
+        let (tangent, bitangent, normal) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        material.perturb_normal(&normal, &tangent, &bitangent, (0.5, 0.5), &Vec3::zeros())
+    }
+
+    #[test]
+    fn normal_map_conventions_flip_the_green_channel() {
+        let opengl = perturbed(NormalMapConvention::OpenGl);
+        let directx = perturbed(NormalMapConvention::DirectX);
+
+        // OpenGL sube en la imagen, que es contra la bitangente (v crece hacia abajo)
+        assert!(opengl.y < 0.0 && directx.y > 0.0, "{:?} {:?}", opengl, directx);
+        assert!((opengl.y + directx.y).abs() < 1e-6);
+        assert!((opengl.x - directx.x).abs() < 1e-6 && (opengl.z - directx.z).abs() < 1e-6);
+    }
+
+    #[test]
+    fn presets_set_their_weights() {
+        let matte = MaterialBuilder::matte(Color::new(0.2, 0.4, 0.6)).build();
+        assert_eq!(matte.diffuse.b, 0.6);
+        assert_eq!((matte.diffuse_weight, matte.specular_weight, matte.reflectivity), (0.9, 0.1, 0.0));
+
+        let mirror = MaterialBuilder::mirror().build();
+        assert_eq!((mirror.diffuse_weight, mirror.reflectivity, mirror.transmission), (0.0, 0.9, 0.0));
+
+        let glass = MaterialBuilder::glass().build();
+        assert_eq!((glass.transmission, glass.refractive_index), (0.9, 1.5));
+
+        let water = MaterialBuilder::water().build();
+        assert_eq!((water.reflectivity, water.refractive_index), (0.5, 1.33));
+
+        let lamp = MaterialBuilder::emissive(Color::new(1.0, 0.5, 0.0)).build();
+        assert!(lamp.is_emissive());
     }
 }
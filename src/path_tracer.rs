@@ -0,0 +1,151 @@
+use nalgebra_glm::Vec3;
+
+use crate::color::Color;
+use crate::integrator::Integrator;
+use crate::pbr::PbrSurface;
+use crate::ray::Ray;
+use crate::ray_intersect::Intersect;
+use crate::sampling::{power_heuristic, uniform_hemisphere, uniform_hemisphere_pdf, Rng};
+use crate::scene::Scene;
+
+// Luz directa de las luces puntuales (deltas, no necesitan MIS)
+fn sample_point_lights(scene: &Scene, intersect: &Intersect, surface: &PbrSurface, view: &Vec3) -> Color {
+    let mut result = Color::black();
+
+    for light in &scene.lights {
+        let to_light = light.position - intersect.point;
+        let distance = to_light.magnitude();
+        let light_dir = to_light / distance;
+        if intersect.normal.dot(&light_dir) <= 0.0 || scene.is_occluded(intersect, &light_dir, distance) {
+            continue;
+        }
+
+        // Misma escala que Whitted: la luz entrega PI * intensidad sobre una superficie difusa
+        let brdf_cos = surface.evaluate(&intersect.normal, view, &light_dir) * intersect.parallax_shadow(&light_dir);
+        result = result + brdf_cos * light.color * (light.intensity * std::f32::consts::PI);
+    }
+
+    result
+}
+
+// Luz directa del mapa de entorno, combinada con el muestreo de la BRDF por MIS
+fn sample_environment(scene: &Scene, intersect: &Intersect, surface: &PbrSurface, view: &Vec3, rng: &mut Rng) -> Color {
+    let light_dir = uniform_hemisphere(&intersect.normal, rng);
+    let brdf_cos = surface.evaluate(&intersect.normal, view, &light_dir);
+    if brdf_cos.r + brdf_cos.g + brdf_cos.b <= 0.0 || scene.is_occluded(intersect, &light_dir, f32::INFINITY) {
+        return Color::black();
+    }
+
+    let light_pdf = uniform_hemisphere_pdf();
+    let bsdf_pdf = surface.pdf(&intersect.normal, view, &light_dir);
+    let weight = power_heuristic(light_pdf, bsdf_pdf);
+
+    scene.environment.radiance(&light_dir) * brdf_cos * (surface.occlusion * weight / light_pdf)
+}
+
+// Luz directa de los objetos emisivos, combinada con el muestreo de la BRDF por MIS
+fn sample_emitters(scene: &Scene, intersect: &Intersect, surface: &PbrSurface, view: &Vec3, rng: &mut Rng) -> Color {
+    let Some(sample) = scene.sample_emitter(intersect, rng) else {
+        return Color::black();
+    };
+
+    let brdf_cos = surface.evaluate(&intersect.normal, view, &sample.direction);
+    if brdf_cos.r + brdf_cos.g + brdf_cos.b <= 0.0 || scene.is_occluded(intersect, &sample.direction, sample.distance) {
+        return Color::black();
+    }
+
+    let bsdf_pdf = surface.pdf(&intersect.normal, view, &sample.direction);
+    let weight = power_heuristic(sample.pdf, bsdf_pdf);
+
+    sample.emission * brdf_cos * (weight / sample.pdf)
+}
+
+pub struct PathTracer {
+    pub max_bounces: u32,
+    pub roulette_start: u32,
+}
+
+impl PathTracer {
+    pub fn new(max_bounces: u32, roulette_start: u32) -> Self {
+        PathTracer {
+            max_bounces,
+            roulette_start,
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn name(&self) -> &str {
+        "Path tracer"
+    }
+
+    fn is_progressive(&self) -> bool {
+        true
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, rng: &mut Rng) -> Color {
+        let environment = &scene.environment;
+        let mut radiance = Color::black();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // Densidad de la BRDF en el último rebote, None si fue especular perfecto o es el rayo primario
+        let mut last_bsdf_pdf: Option<f32> = None;
+        // Oclusión del mapa AO donde salió el rayo; el entorno se atenúa igual que en sample_environment
+        let mut last_occlusion = 1.0;
+
+        for bounce in 0..self.max_bounces {
+            let Some((intersect, object_index)) = scene.intersect_object(&ray) else {
+                let background = match last_bsdf_pdf {
+                    None if bounce == 0 => environment.background(&ray),
+                    None => environment.radiance(&ray.direction),
+                    Some(bsdf_pdf) => {
+                        environment.radiance(&ray.direction) * (last_occlusion * power_heuristic(bsdf_pdf, uniform_hemisphere_pdf()))
+                    }
+                };
+                radiance = radiance + throughput * background;
+                break;
+            };
+
+            let surface = intersect.material.pbr_surface(&intersect.texture_coords());
+
+            // Si el emisor también se muestrea directamente, su emisión se pondera por MIS
+            let emission_weight = match last_bsdf_pdf {
+                Some(bsdf_pdf) => power_heuristic(bsdf_pdf, scene.emitter_pdf(object_index, &ray.direction, &intersect)),
+                None => 1.0,
+            };
+            radiance = radiance + throughput * surface.emission * emission_weight;
+
+            // Con probabilidad igual a la transmisión el rayo atraviesa la superficie
+            let sample = if rng.next_f32() < surface.transmission {
+                surface.sample_transmission(&intersect.normal, &ray.direction, rng)
+            } else {
+                // Las superficies opacas se ven desde el lado de la normal
+                let view = -ray.direction;
+                radiance = radiance + throughput * sample_point_lights(scene, &intersect, &surface, &view);
+                radiance = radiance + throughput * sample_environment(scene, &intersect, &surface, &view, rng);
+                radiance = radiance + throughput * sample_emitters(scene, &intersect, &surface, &view, rng);
+
+                match surface.sample(&intersect.normal, &view, rng) {
+                    Some(sample) => sample,
+                    None => break,
+                }
+            };
+
+            throughput = throughput * sample.weight;
+            last_bsdf_pdf = if sample.is_delta { None } else { Some(sample.pdf) };
+            last_occlusion = surface.occlusion;
+            ray = intersect.spawn_ray(&sample.direction);
+
+            // Ruleta rusa para cortar caminos que ya aportan poco
+            if bounce >= self.roulette_start {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(0.95);
+                if rng.next_f32() >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+
+        radiance
+    }
+}
@@ -0,0 +1,381 @@
+use nalgebra_glm::{reflect_vec, Vec3};
+use std::f32::consts::PI;
+use std::rc::Rc;
+
+use crate::color::Color;
+use crate::material::Material;
+use crate::sampling::{cosine_hemisphere, cosine_hemisphere_pdf, to_world, Rng};
+use crate::texture::{Texture, TextureCoords};
+
+// Material físico metallic-roughness (estilo glTF)
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emission: Color,
+    pub ior: f32,
+    pub transmission: f32,
+    pub base_color_map: Option<Rc<dyn Texture>>,
+    pub roughness_map: Option<Rc<dyn Texture>>,  // Canal rojo
+    pub metallic_map: Option<Rc<dyn Texture>>,  // Canal rojo
+    pub ao_map: Option<Rc<dyn Texture>>,  // Canal rojo
+}
+
+// Parámetros ya evaluados en un punto de la superficie
+#[derive(Debug, Clone, Copy)]
+pub struct PbrSurface {
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub occlusion: f32,
+    pub emission: Color,
+    pub ior: f32,
+    pub transmission: f32,
+}
+
+impl PbrMaterial {
+    pub fn new(base_color: Color, metallic: f32, roughness: f32) -> Self {
+        PbrMaterial {
+            base_color,
+            metallic,
+            roughness,
+            emission: Color::black(),
+            ior: 1.5,
+            transmission: 0.0,
+            base_color_map: None,
+            roughness_map: None,
+            metallic_map: None,
+            ao_map: None,
+        }
+    }
+
+    pub fn surface(&self, coords: &TextureCoords) -> PbrSurface {
+        let base_color = match &self.base_color_map {
+            Some(map) => self.base_color * coords.sample(map.as_ref()),
+            None => self.base_color,
+        };
+        let roughness = self.roughness_map.as_ref().map_or(self.roughness, |map| self.roughness * coords.sample(map.as_ref()).r);
+        let metallic = self.metallic_map.as_ref().map_or(self.metallic, |map| self.metallic * coords.sample(map.as_ref()).r);
+        let occlusion = self.ao_map.as_ref().map_or(1.0, |map| coords.sample(map.as_ref()).r);
+
+        PbrSurface {
+            base_color,
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+            occlusion,
+            emission: self.emission,
+            ior: self.ior,
+            transmission: self.transmission.clamp(0.0, 1.0),
+        }
+    }
+}
+
+// Conversión aproximada desde los parámetros de Material::new
+impl From<&Material> for PbrMaterial {
+    fn from(material: &Material) -> Self {
+        let reflectivity = material.reflectivity.clamp(0.0, 1.0);
+        // Los materiales reflectivos del modelo viejo se tratan como metal pulido
+        let roughness = if reflectivity > 0.0 {
+            material.roughness.max(0.05)
+        } else {
+            (2.0 / (material.specular.max(0.0) + 2.0)).sqrt()
+        };
+
+        PbrMaterial {
+            // La textura de color pasa a ser el mapa de color base
+            base_color: match material.texture {
+                Some(_) => Color::new(1.0, 1.0, 1.0),
+                None => material.diffuse,
+            } * material.diffuse_weight.max(reflectivity),
+            metallic: reflectivity,
+            roughness,
+            emission: material.emission.unwrap_or(Color::black()),
+            ior: if material.refractive_index > 0.0 { material.refractive_index } else { 1.5 },
+            transmission: material.transmission.clamp(0.0, 1.0),
+            base_color_map: material.texture.clone(),
+            roughness_map: None,
+            metallic_map: None,
+            ao_map: None,
+        }
+    }
+}
+
+fn luminance(color: &Color) -> f32 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+fn alpha(roughness: f32) -> f32 {
+    (roughness * roughness).max(1e-3)
+}
+
+// Distribución de microfacetas GGX / Trowbridge-Reitz
+pub fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+// Término de sombreado de Smith para GGX en una dirección
+pub fn smith_g1(n_dot_x: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    2.0 * n_dot_x / (n_dot_x + (a2 + (1.0 - a2) * n_dot_x * n_dot_x).sqrt())
+}
+
+pub fn fresnel_schlick(cos_theta: f32, f0: Color) -> Color {
+    let factor = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    f0 + (Color::new(1.0, 1.0, 1.0) - f0) * factor
+}
+
+// Fresnel dieléctrico exacto, eta = ior_incidente / ior_transmitido
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let r_perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+// Refleja la vista sobre una microfaceta muestreada con la distribución GGX
+pub fn sample_ggx_reflection(normal: &Vec3, view: &Vec3, roughness: f32, rng: &mut Rng) -> Vec3 {
+    let a = alpha(roughness);
+    let u1 = rng.next_f32();
+    let u2 = rng.next_f32();
+    let cos_theta = ((1.0 - u1) / (1.0 + (a * a - 1.0) * u1)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    let half = to_world(&Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta), normal);
+    reflect_vec(&-view, &half).normalize()
+}
+
+pub struct BsdfSample {
+    pub direction: Vec3,
+    // f * cos / pdf
+    pub weight: Color,
+    pub pdf: f32,
+    pub is_delta: bool,
+}
+
+impl PbrSurface {
+    fn f0(&self) -> Color {
+        let dielectric = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        mix(Color::new(dielectric, dielectric, dielectric), self.base_color, self.metallic)
+    }
+
+    fn specular_probability(&self, n_dot_v: f32) -> f32 {
+        let specular = luminance(&fresnel_schlick(n_dot_v, self.f0()));
+        let diffuse = luminance(&self.base_color) * (1.0 - self.metallic);
+        if specular + diffuse <= 0.0 {
+            return 0.5;
+        }
+        (specular / (specular + diffuse)).clamp(0.1, 0.9)
+    }
+
+    // BRDF por coseno, sin la parte de transmisión
+    pub fn evaluate(&self, normal: &Vec3, view: &Vec3, light: &Vec3) -> Color {
+        let n_dot_l = normal.dot(light);
+        let n_dot_v = normal.dot(view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Color::black();
+        }
+
+        let half = (view + light).normalize();
+        let n_dot_h = normal.dot(&half).max(0.0);
+        let v_dot_h = view.dot(&half).max(0.0);
+        let a = alpha(self.roughness);
+
+        let fresnel = fresnel_schlick(v_dot_h, self.f0());
+        let d = ggx_distribution(n_dot_h, a);
+        let g = smith_g1(n_dot_v, a) * smith_g1(n_dot_l, a);
+        let specular = fresnel * (d * g / (4.0 * n_dot_v * n_dot_l));
+
+        let kd = (Color::new(1.0, 1.0, 1.0) - fresnel) * (1.0 - self.metallic);
+        let diffuse = kd * self.base_color * (1.0 / PI);
+
+        (diffuse + specular) * n_dot_l
+    }
+
+    pub fn pdf(&self, normal: &Vec3, view: &Vec3, light: &Vec3) -> f32 {
+        let n_dot_l = normal.dot(light);
+        let n_dot_v = normal.dot(view);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return 0.0;
+        }
+
+        let half = (view + light).normalize();
+        let n_dot_h = normal.dot(&half).max(0.0);
+        let v_dot_h = view.dot(&half).max(1e-6);
+        let specular_pdf = ggx_distribution(n_dot_h, alpha(self.roughness)) * n_dot_h / (4.0 * v_dot_h);
+
+        let p = self.specular_probability(n_dot_v);
+        p * specular_pdf + (1.0 - p) * cosine_hemisphere_pdf(n_dot_l)
+    }
+
+    // Muestrea la mezcla de lóbulo GGX y difuso
+    pub fn sample(&self, normal: &Vec3, view: &Vec3, rng: &mut Rng) -> Option<BsdfSample> {
+        let n_dot_v = normal.dot(view);
+        if n_dot_v <= 0.0 {
+            return None;
+        }
+
+        let direction = if rng.next_f32() < self.specular_probability(n_dot_v) {
+            sample_ggx_reflection(normal, view, self.roughness, rng)
+        } else {
+            cosine_hemisphere(normal, rng)
+        };
+
+        let pdf = self.pdf(normal, view, &direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            direction,
+            weight: self.evaluate(normal, view, &direction) * (1.0 / pdf),
+            pdf,
+            is_delta: false,
+        })
+    }
+
+    // Transmisión lisa: refleja o refracta según Fresnel
+    pub fn sample_transmission(&self, normal: &Vec3, direction: &Vec3, rng: &mut Rng) -> BsdfSample {
+        let entering = direction.dot(normal) < 0.0;
+        let (n, eta) = if entering { (*normal, 1.0 / self.ior) } else { (-normal, self.ior) };
+        let cos_i = -direction.dot(&n);
+        let reflectance = fresnel_dielectric(cos_i, eta);
+
+        let direction = if rng.next_f32() < reflectance {
+            reflect_vec(direction, &n).normalize()
+        } else {
+            let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+            let cos_t = (1.0 - sin2_t).max(0.0).sqrt();
+            (direction * eta + n * (eta * cos_i - cos_t)).normalize()
+        };
+
+        BsdfSample {
+            direction,
+            weight: self.base_color,
+            pdf: 1.0,
+            is_delta: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialBuilder;
+    use crate::texture::TextureMapping;
+
+    #[derive(Debug)]
+    struct FlatTexture(Color);
+
+    impl Texture for FlatTexture {
+        fn evaluate(&self, _uv: (f32, f32), _point: &Vec3) -> Color {
+            self.0
+        }
+    }
+
+    fn assert_close(a: f32, b: f32, tolerance: f32) {
+        assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn ggx_integrates_to_one_over_projected_hemisphere() {
+        // Con mu = cos(theta): 2 PI * integral de D(mu) * mu en [0, 1]
+        for alpha in [0.05, 0.2, 0.5, 1.0] {
+            let steps = 200_000;
+            let integral: f32 = (0..steps)
+                .map(|i| {
+                    let mu = (i as f32 + 0.5) / steps as f32;
+                    ggx_distribution(mu, alpha) * mu
+                })
+                .sum::<f32>()
+                * 2.0
+                * PI
+                / steps as f32;
+            assert_close(integral, 1.0, 1e-2);
+        }
+    }
+
+    #[test]
+    fn smith_g1_is_one_at_normal_incidence_and_zero_at_grazing() {
+        for alpha in [0.05, 0.5, 1.0] {
+            assert_close(smith_g1(1.0, alpha), 1.0, 1e-6);
+            assert_close(smith_g1(0.0, alpha), 0.0, 1e-6);
+            let middle = smith_g1(0.5, alpha);
+            assert!((0.0..=1.0).contains(&middle));
+        }
+    }
+
+    #[test]
+    fn schlick_goes_from_f0_to_white() {
+        let f0 = Color::new(0.04, 0.5, 0.9);
+        let normal = fresnel_schlick(1.0, f0);
+        let grazing = fresnel_schlick(0.0, f0);
+        for (value, expected) in [(normal.r, f0.r), (normal.g, f0.g), (normal.b, f0.b)] {
+            assert_close(value, expected, 1e-6);
+        }
+        for value in [grazing.r, grazing.g, grazing.b] {
+            assert_close(value, 1.0, 1e-6);
+        }
+    }
+
+    #[test]
+    fn dielectric_fresnel_limits() {
+        let eta: f32 = 1.0 / 1.5;
+        assert_close(fresnel_dielectric(1.0, eta), ((1.0 - eta) / (1.0 + eta)).powi(2), 1e-6);
+        assert_close(fresnel_dielectric(0.0, eta), 1.0, 1e-6);
+        // Desde adentro del vidrio, pasado el ángulo crítico hay reflexión total
+        assert_close(fresnel_dielectric(0.1, 1.5), 1.0, 1e-6);
+    }
+
+    #[test]
+    fn textured_material_converts_texture_to_base_color_map() {
+        let material = MaterialBuilder::new()
+            .diffuse(Color::new(0.2, 0.3, 0.4))
+            .texture(Rc::new(FlatTexture(Color::new(0.5, 0.5, 0.5))))
+            .build();
+        let pbr = PbrMaterial::from(&material);
+
+        assert!(pbr.base_color_map.is_some());
+        assert_close(pbr.base_color.r, material.diffuse_weight, 1e-6);
+        assert_close(pbr.metallic, 0.0, 1e-6);
+
+        let coords = TextureCoords::new((0.5, 0.5), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), material.texture_mapping);
+        assert_close(pbr.surface(&coords).base_color.g, 0.5 * material.diffuse_weight, 1e-6);
+    }
+
+    #[test]
+    fn reflective_material_converts_to_polished_metal() {
+        let material = MaterialBuilder::new().diffuse(Color::new(0.8, 0.1, 0.1)).reflectivity(0.9).build();
+        let pbr = PbrMaterial::from(&material);
+
+        assert!(pbr.base_color_map.is_none());
+        assert_close(pbr.metallic, 0.9, 1e-6);
+        assert_close(pbr.roughness, 0.05, 1e-6);
+        assert_close(pbr.base_color.r, 0.8 * 0.9, 1e-6);
+    }
+
+    #[test]
+    fn explicit_pbr_keeps_the_material_emission() {
+        let emission = Color::new(1.0, 0.6, 0.2);
+        let material = MaterialBuilder::emissive(emission).pbr(PbrMaterial::new(Color::new(0.5, 0.5, 0.5), 0.0, 0.5)).build();
+        let coords = TextureCoords::new((0.5, 0.5), Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), TextureMapping::Uv);
+        let surface = material.pbr_surface(&coords);
+
+        // Lo mismo que devuelve emission_at, que es lo que muestrean las luces de área
+        let sampled = material.emission_at(&coords).expect("el material emite");
+        assert_close(surface.emission.r, sampled.r, 1e-6);
+        assert_close(surface.emission.g, sampled.g, 1e-6);
+        assert_close(surface.emission.b, sampled.b, 1e-6);
+        assert_close(surface.emission.g, 0.6, 1e-6);
+    }
+}
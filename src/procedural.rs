@@ -0,0 +1,389 @@
+use nalgebra_glm::Vec3;
+
+use crate::color::Color;
+use crate::texture::Texture;
+
+// Texturas procedurales: no necesitan archivos y se evalúan en UV o en coordenadas de mundo
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSpace {
+    #[allow(dead_code)]  // La escena solo usa texturas en mundo
+    Uv,
+    World,
+}
+
+impl TextureSpace {
+    fn position(&self, uv: (f32, f32), point: &Vec3, scale: f32) -> Vec3 {
+        match self {
+            TextureSpace::Uv => Vec3::new(uv.0, uv.1, 0.0) * scale,
+            TextureSpace::World => point * scale,
+        }
+    }
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    a * (1.0 - t) + b * t
+}
+
+// Hash entero de una celda de la grilla
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8DA6_B343)
+        ^ (y as u32).wrapping_mul(0xD816_3841)
+        ^ (z as u32).wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5BD1_E995);
+    h ^ (h >> 15)
+}
+
+fn hash_to_unit(h: u32) -> f32 {
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// Producto con uno de los 12 gradientes de Perlin elegido por el hash
+fn gradient(h: u32, x: f32, y: f32, z: f32) -> f32 {
+    let h = h & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+// Ruido de Perlin mejorado, aproximadamente en [-1, 1]
+pub fn perlin(p: &Vec3) -> f32 {
+    let cell = p.map(|c| c.floor());
+    let (xi, yi, zi) = (cell.x as i32, cell.y as i32, cell.z as i32);
+    let (x, y, z) = (p.x - cell.x, p.y - cell.y, p.z - cell.z);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        gradient(hash(xi + dx, yi + dy, zi + dz), x - dx as f32, y - dy as f32, z - dz as f32)
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+    lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
+}
+
+// Suma de octavas de ruido, cada una al doble de frecuencia y la mitad de amplitud
+pub fn fbm(p: &Vec3, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * perlin(&(p * frequency));
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum
+}
+
+// Como fbm pero con el valor absoluto, que deja pliegues marcados
+pub fn turbulence(p: &Vec3, octaves: u32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.0;
+    for _ in 0..octaves {
+        sum += amplitude * perlin(&(p * frequency)).abs();
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    sum
+}
+
+// Checker, Noise, Wood y Voronoi no aparecen en la escena de ejemplo; las cubren las pruebas de abajo
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Checker {
+    pub even: Color,
+    pub odd: Color,
+    pub scale: f32,
+    pub space: TextureSpace,
+}
+
+#[allow(dead_code)]
+impl Checker {
+    pub fn new(even: Color, odd: Color, scale: f32, space: TextureSpace) -> Self {
+        Checker { even, odd, scale, space }
+    }
+}
+
+impl Texture for Checker {
+    fn evaluate(&self, uv: (f32, f32), point: &Vec3) -> Color {
+        let p = self.space.position(uv, point, self.scale);
+        let parity = p.x.floor() as i32 + p.y.floor() as i32 + p.z.floor() as i32;
+        if parity.rem_euclid(2) == 0 { self.even } else { self.odd }
+    }
+
+    fn is_solid(&self) -> bool {
+        self.space == TextureSpace::World
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub low: Color,
+    pub high: Color,
+    pub scale: f32,
+    pub octaves: u32,
+    pub space: TextureSpace,
+}
+
+#[allow(dead_code)]
+impl Noise {
+    pub fn new(low: Color, high: Color, scale: f32, space: TextureSpace) -> Self {
+        Noise { low, high, scale, octaves: 5, space }
+    }
+}
+
+impl Texture for Noise {
+    fn evaluate(&self, uv: (f32, f32), point: &Vec3) -> Color {
+        let p = self.space.position(uv, point, self.scale);
+        let t = (fbm(&p, self.octaves) * 0.5 + 0.5).clamp(0.0, 1.0);
+        mix(self.low, self.high, t)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.space == TextureSpace::World
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Marble {
+    pub base: Color,
+    pub vein: Color,
+    pub scale: f32,
+    pub turbulence: f32,  // Cuánto se deforman las vetas
+    pub space: TextureSpace,
+}
+
+impl Marble {
+    pub fn new(base: Color, vein: Color, scale: f32, space: TextureSpace) -> Self {
+        Marble { base, vein, scale, turbulence: 5.0, space }
+    }
+}
+
+impl Texture for Marble {
+    fn evaluate(&self, uv: (f32, f32), point: &Vec3) -> Color {
+        let p = self.space.position(uv, point, self.scale);
+        let stripes = (p.x + self.turbulence * turbulence(&p, 6)).sin();
+        // Vetas finas donde el seno pasa por cero
+        let t = (1.0 - stripes.abs()).powi(6);
+        mix(self.base, self.vein, t)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.space == TextureSpace::World
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Wood {
+    pub light: Color,
+    pub dark: Color,
+    pub scale: f32,
+    pub rings: f32,  // Anillos por unidad de radio
+    pub space: TextureSpace,
+}
+
+#[allow(dead_code)]
+impl Wood {
+    pub fn new(light: Color, dark: Color, scale: f32, space: TextureSpace) -> Self {
+        Wood { light, dark, scale, rings: 8.0, space }
+    }
+}
+
+impl Texture for Wood {
+    fn evaluate(&self, uv: (f32, f32), point: &Vec3) -> Color {
+        let p = self.space.position(uv, point, self.scale);
+        // Anillos concéntricos alrededor del eje Y, deformados con un poco de ruido
+        let radius = (p.x * p.x + p.z * p.z).sqrt() + 0.15 * fbm(&(p * 2.0), 3);
+        // Cerca del eje el ruido puede dejar el radio negativo; rem_euclid mantiene el anillo en [0, 1)
+        let ring = (radius * self.rings).rem_euclid(1.0);
+        let t = (ring * 2.0 - 1.0).abs().powf(0.5);
+        mix(self.dark, self.light, t)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.space == TextureSpace::World
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Voronoi {
+    pub cell: Color,
+    pub edge: Color,
+    pub scale: f32,
+    pub edge_width: f32,
+    pub space: TextureSpace,
+}
+
+#[allow(dead_code)]
+impl Voronoi {
+    pub fn new(cell: Color, edge: Color, scale: f32, space: TextureSpace) -> Self {
+        Voronoi { cell, edge, scale, edge_width: 0.08, space }
+    }
+
+    // Distancias al punto de rasgo más cercano y al segundo, más el hash de la celda ganadora
+    fn distances(p: &Vec3) -> (f32, f32, u32) {
+        let base = p.map(|c| c.floor());
+        let mut nearest = f32::INFINITY;
+        let mut second = f32::INFINITY;
+        let mut nearest_hash = 0;
+
+        for dz in -1..=1 {
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let cell = base + Vec3::new(dx as f32, dy as f32, dz as f32);
+                    let h = hash(cell.x as i32, cell.y as i32, cell.z as i32);
+                    let feature = cell
+                        + Vec3::new(
+                            hash_to_unit(h),
+                            hash_to_unit(h.wrapping_mul(0x27D4_EB2F)),
+                            hash_to_unit(h.wrapping_mul(0x1656_67B1)),
+                        );
+                    let distance = (feature - p).magnitude();
+                    if distance < nearest {
+                        second = nearest;
+                        nearest = distance;
+                        nearest_hash = h;
+                    } else if distance < second {
+                        second = distance;
+                    }
+                }
+            }
+        }
+
+        (nearest, second, nearest_hash)
+    }
+}
+
+impl Texture for Voronoi {
+    fn evaluate(&self, uv: (f32, f32), point: &Vec3) -> Color {
+        let p = self.space.position(uv, point, self.scale);
+        let (nearest, second, cell_hash) = Voronoi::distances(&p);
+
+        // Cada celda con un brillo algo distinto y bordes donde F2 - F1 es chico
+        let shade = 0.75 + 0.25 * hash_to_unit(cell_hash);
+        let edge = 1.0 - ((second - nearest) / self.edge_width).clamp(0.0, 1.0);
+        mix(self.cell * shade, self.edge, edge)
+    }
+
+    fn is_solid(&self) -> bool {
+        self.space == TextureSpace::World
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Puntos repartidos por varias celdas de la grilla, incluidas coordenadas negativas
+    fn sample_points() -> impl Iterator<Item = Vec3> {
+        (0..2000).map(|i| {
+            let i = i as f32;
+            Vec3::new((i * 0.37).sin() * 9.0, (i * 0.11).cos() * 7.0, i * 0.013 - 13.0)
+        })
+    }
+
+    #[test]
+    fn checker_alternates_between_neighbouring_cells() {
+        let even = Color::new(1.0, 1.0, 1.0);
+        let odd = Color::new(0.0, 0.0, 0.0);
+        let checker = Checker::new(even, odd, 1.0, TextureSpace::World);
+
+        let at = |x: f32, y: f32, z: f32| checker.evaluate((0.0, 0.0), &Vec3::new(x, y, z)).r;
+        assert_eq!(at(0.5, 0.5, 0.5), 1.0);
+        assert_eq!(at(1.5, 0.5, 0.5), 0.0);
+        assert_eq!(at(1.5, 1.5, 0.5), 1.0);
+        // Del otro lado del cero la paridad sigue alternando
+        assert_eq!(at(-0.5, 0.5, 0.5), 0.0);
+        assert_eq!(at(-1.5, -0.5, 0.5), 0.0);
+    }
+
+    #[test]
+    fn checker_in_uv_space_ignores_the_point() {
+        let checker = Checker::new(Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0), 4.0, TextureSpace::Uv);
+        let far = Vec3::new(100.3, -7.2, 3.9);
+
+        assert_eq!(checker.evaluate((0.1, 0.1), &far).r, 1.0);
+        assert_eq!(checker.evaluate((0.3, 0.1), &far).b, 1.0);
+        assert_eq!(checker.evaluate((0.3, 0.1), &Vec3::zeros()).b, 1.0);
+    }
+
+    #[test]
+    fn perlin_is_zero_on_lattice_points() {
+        for (x, y, z) in [(0, 0, 0), (3, -2, 5), (-7, 1, -1)] {
+            assert_eq!(perlin(&Vec3::new(x as f32, y as f32, z as f32)), 0.0);
+        }
+    }
+
+    #[test]
+    fn fbm_and_turbulence_stay_in_range() {
+        for octaves in [1, 4, 6] {
+            // Con amplitudes 1/2, 1/4... la suma no pasa de 1 en valor absoluto
+            for p in sample_points() {
+                let value = fbm(&p, octaves);
+                assert!((-1.0..=1.0).contains(&value), "fbm {} en {:?}", value, p);
+                let value = turbulence(&p, octaves);
+                assert!((0.0..=1.0).contains(&value), "turbulence {} en {:?}", value, p);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_mixes_between_its_two_colors() {
+        let noise = Noise::new(Color::new(0.2, 0.2, 0.2), Color::new(0.8, 0.8, 0.8), 3.0, TextureSpace::World);
+        for p in sample_points() {
+            let color = noise.evaluate((0.0, 0.0), &p);
+            assert!((0.2..=0.8).contains(&color.r), "{:?} en {:?}", color, p);
+        }
+    }
+
+    #[test]
+    fn voronoi_nearest_is_never_farther_than_second() {
+        for p in sample_points() {
+            let (nearest, second, _) = Voronoi::distances(&p);
+            assert!(nearest <= second, "F1 {} > F2 {} en {:?}", nearest, second, p);
+            // El punto de rasgo de la propia celda está a menos de una diagonal
+            assert!(nearest <= 3.0f32.sqrt());
+        }
+    }
+
+    #[test]
+    fn voronoi_marks_edges_between_cells() {
+        let voronoi = Voronoi::new(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0), 1.0, TextureSpace::World);
+        let darkest = sample_points().map(|p| voronoi.evaluate((0.0, 0.0), &p).r).fold(f32::INFINITY, f32::min);
+        let brightest = sample_points().map(|p| voronoi.evaluate((0.0, 0.0), &p).r).fold(0.0, f32::max);
+
+        assert!(darkest < 0.2, "ningún punto cayó cerca de un borde");
+        assert!(brightest >= 0.75);
+    }
+
+    #[test]
+    fn wood_rings_stay_between_dark_and_light() {
+        let wood = Wood::new(Color::new(0.9, 0.7, 0.5), Color::new(0.4, 0.25, 0.1), 1.0, TextureSpace::World);
+        // Sobre el eje de los anillos el ruido deja el radio negativo
+        let on_axis = (0..200).map(|i| Vec3::new(0.0, i as f32 * 0.037 - 3.7, 0.0));
+        for p in sample_points().chain(on_axis) {
+            let color = wood.evaluate((0.0, 0.0), &p);
+            assert!((0.4..=0.9).contains(&color.r), "{:?} en {:?}", color, p);
+            assert!((0.25..=0.7).contains(&color.g), "{:?} en {:?}", color, p);
+            assert!((0.1..=0.5).contains(&color.b), "{:?} en {:?}", color, p);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use nalgebra_glm::Vec3;
+
+// Direcciones de los rayos vecinos un pixel a la derecha (x) y un pixel abajo (y), para estimar la huella del rayo.
+// La cámara es estenopeica, así que los vecinos salen del mismo origen
+#[derive(Debug, Clone, Copy)]
+pub struct RayDifferentials {
+    pub x_direction: Vec3,
+    pub y_direction: Vec3,
+}
+
+// Solo cuentan los impactos con t entre tmin y tmax
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+    // 1 / direction por componente (infinito en los ejes donde la dirección es cero), para las pruebas de slabs
+    pub inverse_direction: Vec3,
+    pub tmin: f32,
+    pub tmax: f32,
+    pub differentials: Option<RayDifferentials>,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray {
+            origin,
+            direction,
+            inverse_direction: direction.map(|c| 1.0 / c),
+            tmin: 0.0,
+            tmax: f32::INFINITY,
+            differentials: None,
+        }
+    }
+
+    pub fn with_range(mut self, tmin: f32, tmax: f32) -> Self {
+        self.tmin = tmin;
+        self.tmax = tmax;
+        self
+    }
+
+    pub fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    pub fn contains(&self, t: f32) -> bool {
+        t >= self.tmin && t <= self.tmax
+    }
+}
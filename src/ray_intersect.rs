@@ -1,37 +1,220 @@
 use nalgebra_glm::Vec3;
+use std::cell::Cell;
+use std::rc::Rc;
+use crate::bounds::Aabb;
+use crate::color::Color;
 use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampling::Rng;
+use crate::texture::TextureCoords;
 
+// Los rayos secundarios ignoran impactos más cerca que esto, así no vuelven a chocar con la superficie de la que salen
+pub const RAY_EPSILON: f32 = 1e-4;
+
+thread_local! {
+    // Pruebas de rayo contra cajas y primitivas, para el mapa de calor de depuración
+    static INTERSECTION_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+// Cada prueba de caja, triángulo o paso de sphere tracing suma una
+pub fn count_intersection_test() {
+    INTERSECTION_TESTS.with(|tests| tests.set(tests.get() + 1));
+}
+
+pub fn intersection_tests() -> u64 {
+    INTERSECTION_TESTS.with(Cell::get)
+}
+
+pub fn reset_intersection_tests() {
+    INTERSECTION_TESTS.with(|tests| tests.set(0));
+}
+
+// Datos geométricos del impacto. El material se guarda como referencia y recién se evalúa al sombrear,
+// así solo el impacto más cercano paga por las texturas
 #[derive(Debug, Clone)]
-pub struct Intersect {
+pub struct Intersect<'a> {
     pub point: Vec3,
     pub normal: Vec3,
     pub distance: f32,
-    pub is_intersecting: bool,
-    pub material: Material,
+    pub uv: (f32, f32),
+    // Direcciones en las que crecen u y v; cero si la primitiva no las define
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub material: &'a Material,
 }
 
-impl Intersect {
-    pub fn new(point: Vec3, normal: Vec3, distance: f32, material: Material) -> Self {
+impl<'a> Intersect<'a> {
+    pub fn new(point: Vec3, normal: Vec3, distance: f32, uv: (f32, f32), material: &'a Material) -> Self {
         Intersect {
             point,
             normal,
             distance,
-            is_intersecting: true,
+            uv,
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
             material,
         }
     }
 
-    pub fn empty() -> Self {
-        Intersect {
-            point: Vec3::zeros(),
-            normal: Vec3::zeros(),
-            distance: 0.0,
-            is_intersecting: false,
-            material: Material::black(),
+    pub fn with_tangents(mut self, tangent: Vec3, bitangent: Vec3) -> Self {
+        self.tangent = tangent;
+        self.bitangent = bitangent;
+        self
+    }
+
+    // Desplaza las UV con el height map y ajusta la normal con el normal map, vistos desde el rayo
+    pub fn with_surface_detail(mut self, ray_direction: &Vec3) -> Self {
+        if self.tangent == Vec3::zeros() {
+            return self;
+        }
+        if self.material.height_map.is_some() {
+            let view_tangent = self.to_tangent_space(&-ray_direction);
+            self.uv = self.material.parallax_uv(self.uv, &self.point, &view_tangent);
+        }
+        if self.material.normal_map.is_some() {
+            self.normal = self.material.perturb_normal(&self.normal, &self.tangent, &self.bitangent, self.uv, &self.point);
         }
+        self
+    }
+
+    pub fn texture_coords(&self) -> TextureCoords {
+        TextureCoords::new(self.uv, self.point, self.normal, self.material.texture_mapping)
+    }
+
+    // Color difuso en el punto: el de la textura si hay, si no el color base
+    pub fn diffuse(&self) -> Color {
+        self.material.diffuse_at(&self.texture_coords())
+    }
+
+    pub fn emission(&self) -> Option<Color> {
+        self.material.emission_at(&self.texture_coords())
+    }
+
+    // Pasa una dirección de mundo al espacio tangente (u, v, normal)
+    pub fn to_tangent_space(&self, direction: &Vec3) -> Vec3 {
+        Vec3::new(direction.dot(&self.tangent), direction.dot(&self.bitangent), direction.dot(&self.normal))
+    }
+
+    // Autosombra del height map hacia la luz; 1 si el material no la usa
+    pub fn parallax_shadow(&self, light_dir: &Vec3) -> f32 {
+        if !self.material.parallax_shadows || self.tangent == Vec3::zeros() {
+            return 1.0;
+        }
+        self.material.parallax_shadow(self.uv, &self.point, &self.to_tangent_space(light_dir))
+    }
+
+    // Rayo secundario desde el impacto; arranca en el punto mismo y tmin deja afuera la superficie de la que sale
+    pub fn spawn_ray(&self, direction: &Vec3) -> Ray {
+        Ray::new(self.point, *direction).with_range(RAY_EPSILON, f32::INFINITY)
     }
 }
 
+// Punto muestreado sobre la superficie de un objeto emisivo
+pub struct SurfaceSample {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub uv: (f32, f32),
+    pub emission: Color,
+    // Densidad por unidad de área en mundo con la que se eligió el punto
+    pub pdf: f32,
+}
+
+// Tramo del rayo dentro de un sólido, como distancias de entrada y salida sobre el rayo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub enter: f32,
+    pub exit: f32,
+}
+
 pub trait RayIntersect {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect;
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>>;
+
+    // Todos los tramos del rayo dentro del objeto ordenados por distancia, incluso detrás del origen.
+    // Los objetos que no son sólidos cerrados no devuelven ninguno y no sirven para CSG
+    fn ray_intervals(&self, _ray: &Ray) -> Vec<Interval> {
+        Vec::new()
+    }
+
+    // Impacto completo en un borde que devolvió ray_intervals, con la normal hacia afuera del sólido.
+    // Se arma aparte para no calcular normales ni uv de bordes que después se descartan
+    fn boundary_hit(&self, _ray: &Ray, _distance: f32) -> Option<Intersect<'_>> {
+        None
+    }
+
+    // Los objetos que emiten luz se muestrean como luces de área
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // Área total de la superficie, emita o no; 0 si el objeto no sabe muestrearse
+    fn surface_area(&self) -> f32 {
+        0.0
+    }
+
+    // Punto sobre la superficie; no tiene por qué ser uniforme, la densidad va en la muestra
+    fn sample_surface(&self, _rng: &mut Rng) -> Option<SurfaceSample> {
+        None
+    }
+
+    // Densidad por unidad de área con la que sample_surface elegiría este punto de la superficie;
+    // 0 si el punto no está sobre ella
+    fn surface_pdf(&self, _point: &Vec3, _normal: &Vec3) -> f32 {
+        0.0
+    }
+
+    // Caja envolvente; None si el objeto no tiene límites conocidos
+    fn bounds(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+// Un prototipo compartido por varias instancias se usa a través de un Rc
+impl<T: RayIntersect + ?Sized> RayIntersect for Rc<T> {
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        self.as_ref().ray_intersect(ray)
+    }
+
+    fn ray_intervals(&self, ray: &Ray) -> Vec<Interval> {
+        self.as_ref().ray_intervals(ray)
+    }
+
+    fn boundary_hit(&self, ray: &Ray, distance: f32) -> Option<Intersect<'_>> {
+        self.as_ref().boundary_hit(ray, distance)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.as_ref().is_emissive()
+    }
+
+    fn surface_area(&self) -> f32 {
+        self.as_ref().surface_area()
+    }
+
+    fn sample_surface(&self, rng: &mut Rng) -> Option<SurfaceSample> {
+        self.as_ref().sample_surface(rng)
+    }
+
+    fn surface_pdf(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        self.as_ref().surface_pdf(point, normal)
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.as_ref().bounds()
+    }
+}
+
+// Intersección más cercana y el índice del objeto que la produjo.
+// Cada impacto acorta el rayo, así los objetos más lejanos se descartan por su caja
+pub fn closest_intersect<'a>(objects: &'a [Box<dyn RayIntersect>], ray: &Ray) -> Option<(Intersect<'a>, usize)> {
+    let mut ray = *ray;
+    let mut closest = None;
+
+    for (index, object) in objects.iter().enumerate() {
+        if let Some(intersect) = object.ray_intersect(&ray) {
+            ray.tmax = intersect.distance;
+            closest = Some((intersect, index));
+        }
+    }
+
+    closest
 }
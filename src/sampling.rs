@@ -0,0 +1,91 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Generador PCG32 pequeño, suficiente para el muestreo de Monte Carlo
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    // Semilla distinta por pixel y por cuadro
+    pub fn for_pixel(x: usize, y: usize, frame: u32) -> Self {
+        let seed = (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+            ^ (frame as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+        Rng::new(seed)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Número uniforme en [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+// Base ortonormal alrededor de la normal (Duff et al.)
+pub fn orthonormal_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    let tangent = Vec3::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x);
+    let bitangent = Vec3::new(b, sign + normal.y * normal.y * a, -normal.y);
+    (tangent, bitangent)
+}
+
+pub fn to_world(local: &Vec3, normal: &Vec3) -> Vec3 {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * local.x + bitangent * local.y + normal * local.z).normalize()
+}
+
+// Dirección con densidad cos(theta) / PI
+pub fn cosine_hemisphere(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let u1 = rng.next_f32();
+    let u2 = rng.next_f32();
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let local = Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - u1).max(0.0).sqrt());
+    to_world(&local, normal)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta.max(0.0) / PI
+}
+
+// Dirección con densidad uniforme 1 / (2 PI)
+pub fn uniform_hemisphere(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let z = rng.next_f32();
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.next_f32();
+    let local = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+    to_world(&local, normal)
+}
+
+pub fn uniform_hemisphere_pdf() -> f32 {
+    1.0 / (2.0 * PI)
+}
+
+// Heurística de potencia (beta = 2) para MIS
+pub fn power_heuristic(pdf_a: f32, pdf_b: f32) -> f32 {
+    let a = pdf_a * pdf_a;
+    let b = pdf_b * pdf_b;
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
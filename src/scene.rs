@@ -0,0 +1,126 @@
+use nalgebra_glm::Vec3;
+
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::ray_intersect::{
+    closest_intersect, intersection_tests, reset_intersection_tests, Intersect, RayIntersect, SurfaceSample, RAY_EPSILON,
+};
+use crate::sampling::Rng;
+
+// Muestra de luz de área: punto emisor, dirección hacia él y densidad en ángulo sólido
+pub struct EmitterSample {
+    pub direction: Vec3,
+    pub distance: f32,
+    pub emission: Color,
+    pub pdf: f32,
+}
+
+pub struct Scene {
+    pub objects: Vec<Box<dyn RayIntersect>>,
+    pub lights: Vec<Light>,
+    pub environment: EnvironmentMap,
+    // Índices de los objetos con emisión, usados como luces de área
+    emitters: Vec<usize>,
+}
+
+impl Scene {
+    pub fn new(objects: Vec<Box<dyn RayIntersect>>, lights: Vec<Light>, environment: EnvironmentMap) -> Self {
+        let emitters = objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| object.is_emissive() && object.surface_area() > 0.0)
+            .map(|(index, _)| index)
+            .collect();
+
+        Scene {
+            objects,
+            lights,
+            environment,
+            emitters,
+        }
+    }
+
+    // Pruebas de cajas y primitivas hechas desde el último reinicio, para el mapa de calor
+    pub fn intersection_tests(&self) -> u64 {
+        intersection_tests()
+    }
+
+    pub fn reset_intersection_tests(&self) {
+        reset_intersection_tests();
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        self.intersect_object(ray).map(|(intersect, _)| intersect)
+    }
+
+    // Como intersect, pero también devuelve el índice del objeto alcanzado.
+    // Solo al impacto más cercano se le aplican el height map y el normal map
+    pub fn intersect_object(&self, ray: &Ray) -> Option<(Intersect<'_>, usize)> {
+        closest_intersect(&self.objects, ray).map(|(intersect, index)| (intersect.with_surface_detail(&ray.direction), index))
+    }
+
+    pub fn has_emitters(&self) -> bool {
+        !self.emitters.is_empty()
+    }
+
+    // Elige un emisor al azar y un punto sobre él, visto desde la intersección
+    pub fn sample_emitter(&self, intersect: &Intersect, rng: &mut Rng) -> Option<EmitterSample> {
+        if self.emitters.is_empty() {
+            return None;
+        }
+
+        let choice = ((rng.next_f32() * self.emitters.len() as f32) as usize).min(self.emitters.len() - 1);
+        let object = &self.objects[self.emitters[choice]];
+        let SurfaceSample { point, normal, emission, pdf: area_pdf, .. } = object.sample_surface(rng)?;
+
+        let to_light = point - intersect.point;
+        let distance = to_light.magnitude();
+        if distance <= 0.0 {
+            return None;
+        }
+        let direction = to_light / distance;
+
+        // Solo emite hacia el lado de la normal
+        let cos_light = -direction.dot(&normal);
+        if cos_light <= 0.0 {
+            return None;
+        }
+
+        // Densidad por área del punto pasada a ángulo sólido
+        let pdf = area_pdf * distance * distance / (cos_light * self.emitters.len() as f32);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(EmitterSample {
+            direction,
+            distance,
+            emission,
+            pdf,
+        })
+    }
+
+    // Densidad en ángulo sólido con la que sample_emitter habría elegido el punto alcanzado
+    pub fn emitter_pdf(&self, object_index: usize, ray_direction: &Vec3, intersect: &Intersect) -> f32 {
+        if !self.emitters.contains(&object_index) {
+            return 0.0;
+        }
+
+        let cos_light = -ray_direction.dot(&intersect.normal);
+        if cos_light <= 0.0 {
+            return 0.0;
+        }
+
+        let area_pdf = self.objects[object_index].surface_pdf(&intersect.point, &intersect.normal);
+        area_pdf * intersect.distance * intersect.distance / (cos_light * self.emitters.len() as f32)
+    }
+
+    // Revisa si algo bloquea el rayo de sombra antes de max_distance; el rayo termina ahí,
+    // así lo que está detrás de la luz ni se prueba, y basta con el primer bloqueo que aparezca.
+    // Se deja el mismo margen en los dos extremos para no contar la superficie de una luz de área
+    pub fn is_occluded(&self, intersect: &Intersect, direction: &Vec3, max_distance: f32) -> bool {
+        let ray = intersect.spawn_ray(direction).with_range(RAY_EPSILON, max_distance - RAY_EPSILON);
+        self.objects.iter().any(|object| object.ray_intersect(&ray).is_some())
+    }
+}
@@ -0,0 +1,276 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+use crate::bounds::Aabb;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::ray_intersect::{count_intersection_test, Intersect, RayIntersect};
+
+// Funciones de distancia con signo: negativas dentro, positivas fuera
+
+pub fn sd_sphere(p: &Vec3, radius: f32) -> f32 {
+    p.magnitude() - radius
+}
+
+pub fn sd_box(p: &Vec3, half_size: &Vec3) -> f32 {
+    let q = p.abs() - half_size;
+    q.sup(&Vec3::zeros()).magnitude() + q.x.max(q.y).max(q.z).min(0.0)
+}
+
+pub fn sd_round_box(p: &Vec3, half_size: &Vec3, radius: f32) -> f32 {
+    sd_box(p, &half_size.add_scalar(-radius)) - radius
+}
+
+// Toro acostado en el plano XZ
+pub fn sd_torus(p: &Vec3, major_radius: f32, minor_radius: f32) -> f32 {
+    let ring = Vec3::new(p.x, 0.0, p.z).magnitude() - major_radius;
+    (ring * ring + p.y * p.y).sqrt() - minor_radius
+}
+
+pub fn sd_capsule(p: &Vec3, a: &Vec3, b: &Vec3, radius: f32) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+    (pa - ba * h).magnitude() - radius
+}
+
+// Uniones y restas suaves (Quilez): k es el ancho de la mezcla
+pub fn smooth_union(d1: f32, d2: f32, k: f32) -> f32 {
+    let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+    d2 + (d1 - d2) * h - k * h * (1.0 - h)
+}
+
+// Resta d2 de d1
+pub fn smooth_subtraction(d1: f32, d2: f32, k: f32) -> f32 {
+    let h = (0.5 - 0.5 * (d1 + d2) / k).clamp(0.0, 1.0);
+    d1 + (-d2 - d1) * h + k * h * (1.0 - h)
+}
+
+// Repite el espacio cada spacing, hasta limit copias a cada lado. Un límite o un espaciado de 0 no repite en ese eje
+pub fn repeat(p: &Vec3, spacing: &Vec3, limit: &Vec3) -> Vec3 {
+    let cell = |p: f32, spacing: f32, limit: f32| {
+        if spacing == 0.0 { 0.0 } else { (p / spacing).round().clamp(-limit, limit) }
+    };
+    let cell = Vec3::new(cell(p.x, spacing.x, limit.x), cell(p.y, spacing.y, limit.y), cell(p.z, spacing.z, limit.z));
+    p - spacing.component_mul(&cell)
+}
+
+// Árbol de formas y operaciones que se evalúa como una sola distancia
+pub enum Sdf {
+    Sphere { radius: f32 },
+    Box { half_size: Vec3 },
+    RoundBox { half_size: Vec3, radius: f32 },
+    Torus { major_radius: f32, minor_radius: f32 },
+    Capsule { a: Vec3, b: Vec3, radius: f32 },
+    Translate { offset: Vec3, shape: Box<Sdf> },
+    Repeat { spacing: Vec3, limit: Vec3, shape: Box<Sdf> },
+    SmoothUnion { a: Box<Sdf>, b: Box<Sdf>, k: f32 },
+    SmoothSubtraction { a: Box<Sdf>, b: Box<Sdf>, k: f32 },
+}
+
+impl Sdf {
+    pub fn distance(&self, p: &Vec3) -> f32 {
+        match self {
+            Sdf::Sphere { radius } => sd_sphere(p, *radius),
+            Sdf::Box { half_size } => sd_box(p, half_size),
+            Sdf::RoundBox { half_size, radius } => sd_round_box(p, half_size, *radius),
+            Sdf::Torus { major_radius, minor_radius } => sd_torus(p, *major_radius, *minor_radius),
+            Sdf::Capsule { a, b, radius } => sd_capsule(p, a, b, *radius),
+            Sdf::Translate { offset, shape } => shape.distance(&(p - offset)),
+            Sdf::Repeat { spacing, limit, shape } => shape.distance(&repeat(p, spacing, limit)),
+            Sdf::SmoothUnion { a, b, k } => smooth_union(a.distance(p), b.distance(p), *k),
+            Sdf::SmoothSubtraction { a, b, k } => smooth_subtraction(a.distance(p), b.distance(p), *k),
+        }
+    }
+
+    pub fn translate(self, offset: Vec3) -> Sdf {
+        Sdf::Translate { offset, shape: Box::new(self) }
+    }
+
+    pub fn repeat(self, spacing: Vec3, limit: Vec3) -> Sdf {
+        Sdf::Repeat { spacing, limit, shape: Box::new(self) }
+    }
+
+    pub fn smooth_union(self, other: Sdf, k: f32) -> Sdf {
+        Sdf::SmoothUnion { a: Box::new(self), b: Box::new(other), k }
+    }
+
+    pub fn smooth_subtract(self, other: Sdf, k: f32) -> Sdf {
+        Sdf::SmoothSubtraction { a: Box::new(self), b: Box::new(other), k }
+    }
+
+    // Gradiente por diferencias centrales. En puntos donde se anula (el centro de una esfera,
+    // una arista simétrica) no hay dirección preferida y se devuelve +Y
+    pub fn normal(&self, p: &Vec3) -> Vec3 {
+        let h = 1e-4;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+        Vec3::new(
+            self.distance(&(p + dx)) - self.distance(&(p - dx)),
+            self.distance(&(p + dy)) - self.distance(&(p - dy)),
+            self.distance(&(p + dz)) - self.distance(&(p - dz)),
+        )
+        .try_normalize(1e-12)
+        .unwrap_or(Vec3::new(0.0, 1.0, 0.0))
+    }
+}
+
+// Objeto definido por una distancia con signo, intersectado con sphere tracing dentro de su caja
+pub struct SdfObject {
+    pub shape: Sdf,
+    pub bounds: Aabb,
+    pub material: Material,
+    pub max_steps: u32,
+}
+
+const SURFACE_EPSILON: f32 = 5e-5;
+
+impl SdfObject {
+    pub fn new(shape: Sdf, bounds: Aabb, material: Material) -> Self {
+        SdfObject {
+            shape,
+            bounds,
+            material,
+            max_steps: 128,
+        }
+    }
+}
+
+impl RayIntersect for SdfObject {
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        let (tmin, tmax) = self.bounds.ray_range(ray)?;
+
+        // Se avanza lo que indica la distancia; desde adentro el valor absoluto lleva a la salida
+        let mut t = tmin;
+        // Un rayo secundario arranca pegado a la superficie de la que sale: primero hay que despegarse
+        let mut leaving = ray.tmin > 0.0 && tmin == ray.tmin;
+        for _ in 0..self.max_steps {
+            count_intersection_test();
+            let point = ray.at(t);
+            let distance = self.shape.distance(&point).abs();
+            if leaving && distance < SURFACE_EPSILON {
+                t += SURFACE_EPSILON;
+                continue;
+            }
+            leaving = false;
+            if distance < SURFACE_EPSILON {
+                let normal = self.shape.normal(&point);
+                // UV esféricas a partir de la normal; para texturas conviene la proyección triplanar
+                let uv = (0.5 + normal.z.atan2(normal.x) / (2.0 * PI), normal.y.clamp(-1.0, 1.0).acos() / PI);
+                return Some(Intersect::new(point, normal, t, uv, &self.material));
+            }
+            t += distance;
+            if t > tmax {
+                break;
+            }
+        }
+
+        None
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::MaterialBuilder;
+
+    fn unit_sphere() -> SdfObject {
+        let bounds = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        SdfObject::new(Sdf::Sphere { radius: 1.0 }, bounds, MaterialBuilder::new().build())
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    fn assert_vec_close(a: &Vec3, b: &Vec3) {
+        assert!((a - b).magnitude() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn primitive_distances() {
+        assert_close(sd_sphere(&Vec3::new(3.0, 0.0, 0.0), 1.0), 2.0);
+        assert_close(sd_sphere(&Vec3::zeros(), 1.0), -1.0);
+
+        let half = Vec3::new(1.0, 1.0, 1.0);
+        assert_close(sd_box(&Vec3::new(2.0, 0.0, 0.0), &half), 1.0);
+        assert_close(sd_box(&Vec3::new(2.0, 2.0, 0.0), &half), 2.0_f32.sqrt());
+        assert_close(sd_box(&Vec3::new(0.0, 0.5, 0.0), &half), -0.5);
+
+        // La caja redondeada ocupa lo mismo en las caras y se achica en las esquinas
+        assert_close(sd_round_box(&Vec3::new(2.0, 0.0, 0.0), &half, 0.25), 1.0);
+        assert_close(sd_round_box(&Vec3::new(1.0, 1.0, 1.0), &half, 0.25), 3.0_f32.sqrt() * 0.25 - 0.25);
+
+        assert_close(sd_torus(&Vec3::new(2.0, 0.0, 0.0), 2.0, 0.5), -0.5);
+        assert_close(sd_torus(&Vec3::new(0.0, 0.0, 2.0), 2.0, 0.5), -0.5);
+        assert_close(sd_torus(&Vec3::new(2.0, 1.0, 0.0), 2.0, 0.5), 0.5);
+        assert_close(sd_torus(&Vec3::zeros(), 2.0, 0.5), 1.5);
+
+        let (a, b) = (Vec3::zeros(), Vec3::new(0.0, 2.0, 0.0));
+        assert_close(sd_capsule(&Vec3::new(1.0, 1.0, 0.0), &a, &b, 0.5), 0.5);
+        assert_close(sd_capsule(&Vec3::new(0.0, 3.0, 0.0), &a, &b, 0.5), 0.5);
+        assert_close(sd_capsule(&Vec3::new(0.0, -1.0, 0.0), &a, &b, 0.5), 0.5);
+        assert_close(sd_capsule(&Vec3::new(0.0, 1.0, 0.0), &a, &b, 0.5), -0.5);
+    }
+
+    #[test]
+    fn smooth_operators() {
+        // Lejos de la mezcla son el mínimo y la resta comunes
+        assert_close(smooth_union(1.0, 3.0, 0.1), 1.0);
+        assert_close(smooth_subtraction(0.5, 3.0, 0.1), 0.5);
+        assert_close(smooth_subtraction(0.5, -3.0, 0.1), 3.0);
+        // Donde se igualan la unión se hunde k / 4 por debajo del mínimo
+        assert_close(smooth_union(0.2, 0.2, 0.4), 0.1);
+        for (d1, d2) in [(0.1, 0.3), (-0.2, 0.05), (0.4, -0.1)] {
+            assert!(smooth_union(d1, d2, 0.5) <= d1.min(d2));
+            assert!(smooth_subtraction(d1, d2, 0.5) >= d1.max(-d2));
+        }
+    }
+
+    #[test]
+    fn repeat_wraps_into_the_nearest_copy() {
+        let spacing = Vec3::new(1.0, 1.0, 1.0);
+        let limit = Vec3::new(5.0, 0.0, 5.0);
+        assert_vec_close(&repeat(&Vec3::new(2.1, 0.3, -0.9), &spacing, &limit), &Vec3::new(0.1, 0.3, 0.1));
+        // Pasado el límite se queda con la última copia
+        assert_vec_close(&repeat(&Vec3::new(10.2, 0.0, 0.0), &spacing, &limit), &Vec3::new(5.2, 0.0, 0.0));
+        // Un espaciado de 0 deja el eje como está en lugar de dar NaN
+        let p = repeat(&Vec3::new(0.3, 7.0, 0.2), &Vec3::new(1.0, 0.0, 1.0), &Vec3::new(5.0, 5.0, 5.0));
+        assert_vec_close(&p, &Vec3::new(0.3, 7.0, 0.2));
+    }
+
+    #[test]
+    fn normals_point_out_of_the_surface() {
+        let sphere = Sdf::Sphere { radius: 1.0 }.translate(Vec3::new(0.0, 1.0, 0.0));
+        assert_vec_close(&sphere.normal(&Vec3::new(1.0, 1.0, 0.0)), &Vec3::new(1.0, 0.0, 0.0));
+        let cube = Sdf::Box { half_size: Vec3::new(1.0, 1.0, 1.0) };
+        assert_vec_close(&cube.normal(&Vec3::new(0.2, 1.0, -0.3)), &Vec3::new(0.0, 1.0, 0.0));
+
+        // En el centro el gradiente se anula; la normal sigue siendo unitaria
+        let center = Sdf::Sphere { radius: 1.0 }.normal(&Vec3::zeros());
+        assert!(center.iter().all(|c| c.is_finite()));
+        assert_close(center.magnitude(), 1.0);
+    }
+
+    #[test]
+    fn spawned_rays_leave_their_own_surface() {
+        let sphere = unit_sphere();
+        let hit = sphere
+            .ray_intersect(&Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)))
+            .expect("debería chocar con la esfera");
+
+        // Hacia afuera, también casi rasante, no vuelve a chocar con la esfera
+        for direction in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.02).normalize()] {
+            assert!(sphere.ray_intersect(&hit.spawn_ray(&direction)).is_none(), "{:?}", direction);
+        }
+
+        // Hacia adentro la atraviesa y sale por el otro lado
+        let through = sphere.ray_intersect(&hit.spawn_ray(&Vec3::new(0.0, 0.0, -1.0))).expect("debería salir por detrás");
+        assert!((through.point.z + 1.0).abs() < 1e-3, "{:?}", through.point);
+    }
+}
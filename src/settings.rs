@@ -0,0 +1,27 @@
+// Calidad del render: el modo interactivo prioriza la velocidad, el offline la convergencia
+#[derive(Debug, Clone, Copy)]
+pub struct RenderSettings {
+    pub glossy_samples: u32,
+    // Muestras de luz por punto sobre los objetos emisivos
+    pub emitter_samples: u32,
+    // Cuadros que se acumulan antes de guardar la imagen en modo offline
+    pub frames: u32,
+}
+
+impl RenderSettings {
+    pub fn interactive() -> Self {
+        RenderSettings {
+            glossy_samples: 2,
+            emitter_samples: 1,
+            frames: 1,
+        }
+    }
+
+    pub fn offline() -> Self {
+        RenderSettings {
+            glossy_samples: 16,
+            emitter_samples: 8,
+            frames: 64,
+        }
+    }
+}
@@ -1,35 +1,209 @@
-use image::{DynamicImage, GenericImageView};
-use std::rc::Rc;
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{DynamicImage, ImageError};
+use nalgebra_glm::Vec3;
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
+use std::fs;
+use std::time::SystemTime;
+use crate::color::Color;
 
-#[derive(Debug)] 
-pub struct Texture {
-    image_data: DynamicImage,
+// Cualquier fuente de color para un material: imagen o procedural.
+// Recibe las UV de la superficie y el punto en mundo; cada textura usa lo que necesita
+pub trait Texture: Debug {
+    fn evaluate(&self, uv: (f32, f32), point: &Vec3) -> Color;
+
+    // Si el color sale directamente del punto 3D; solo esas texturas sirven para el mapeo sólido
+    fn is_solid(&self) -> bool {
+        false
+    }
+}
+
+// Cómo se obtienen las coordenadas de textura de un material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureMapping {
+    // UV de la primitiva
+    Uv,
+    // Tres proyecciones planas sobre los ejes mezcladas según la normal
+    Triplanar { scale: f32, sharpness: f32 },
+    // Punto de mundo escalado para las texturas que se evalúan en 3D; las demás caen en triplanar
+    Solid { scale: f32 },
+}
+
+// Todo lo necesario para evaluar una textura en un punto de la superficie
+#[derive(Debug, Clone, Copy)]
+pub struct TextureCoords {
+    pub uv: (f32, f32),
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub mapping: TextureMapping,
+}
+
+impl TextureCoords {
+    pub fn new(uv: (f32, f32), point: Vec3, normal: Vec3, mapping: TextureMapping) -> Self {
+        TextureCoords { uv, point, normal, mapping }
+    }
+
+    pub fn sample(&self, texture: &dyn Texture) -> Color {
+        match self.mapping {
+            TextureMapping::Uv => texture.evaluate(self.uv, &self.point),
+            TextureMapping::Triplanar { scale, sharpness } => self.triplanar(texture, scale, sharpness),
+            TextureMapping::Solid { scale } if texture.is_solid() => texture.evaluate(self.uv, &(self.point * scale)),
+            // Una imagen proyectada en un solo plano se estira en las caras perpendiculares
+            TextureMapping::Solid { scale } => self.triplanar(texture, scale, 4.0),
+        }
+    }
+
+    fn triplanar(&self, texture: &dyn Texture, scale: f32, sharpness: f32) -> Color {
+        let p = self.point * scale;
+        let weights = self.normal.map(|c| c.abs().powf(sharpness));
+        let total = (weights.x + weights.y + weights.z).max(1e-6);
+
+        // v crece hacia abajo en las imágenes, por eso se invierte la altura
+        let x = texture.evaluate((p.z.rem_euclid(1.0), (-p.y).rem_euclid(1.0)), &self.point);
+        let y = texture.evaluate((p.x.rem_euclid(1.0), p.z.rem_euclid(1.0)), &self.point);
+        let z = texture.evaluate((p.x.rem_euclid(1.0), (-p.y).rem_euclid(1.0)), &self.point);
+        (x * weights.x + y * weights.y + z * weights.z) * (1.0 / total)
+    }
+}
+
+// Texeles RGB de 8 bits en un arreglo plano, fila por fila
+#[derive(Debug)]
+struct TexelBuffer {
+    width: usize,
+    height: usize,
+    texels: Vec<[u8; 3]>,
+}
+
+impl TexelBuffer {
+    fn from_image(image_data: &DynamicImage) -> Result<Self, ImageError> {
+        let rgb = image_data.to_rgb8();
+        let (width, height) = rgb.dimensions();
+        check_dimensions(width, height, 1)?;
+        let texels = rgb.pixels().map(|pixel| pixel.0).collect();
+
+        Ok(TexelBuffer {
+            width: width as usize,
+            height: height as usize,
+            texels,
+        })
+    }
+}
+
+// Error si la imagen tiene menos de min pixeles de ancho o de alto
+pub fn check_dimensions(width: u32, height: u32, min: u32) -> Result<(), ImageError> {
+    if width < min || height < min {
+        return Err(ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic(format!(
+            "la imagen mide {}x{} y se necesitan al menos {}x{} pixeles",
+            width, height, min, min
+        )))));
+    }
+    Ok(())
 }
 
-impl Texture {
+#[derive(Debug)]
+pub struct ImageTexture {
+    path: String,
+    // Se puede reemplazar al recargar sin cambiar los Rc que tienen los materiales
+    buffer: RefCell<TexelBuffer>,
+    // Fecha de modificación del archivo en la última carga
+    modified: Cell<Option<SystemTime>>,
+}
+
+impl ImageTexture {
     pub fn new(image_path: &str) -> Self {
-        match image::open(image_path) {
-            Ok(image_data) => Texture { image_data },
+        match image::open(image_path).and_then(|image_data| TexelBuffer::from_image(&image_data)) {
+            Ok(buffer) => ImageTexture {
+                path: image_path.to_string(),
+                buffer: RefCell::new(buffer),
+                modified: Cell::new(modified_time(image_path)),
+            },
             Err(e) => {
                 panic!("Error al cargar la textura desde {}: {}", image_path, e);
             }
         }
     }
 
-    pub fn width(&self) -> u32 {
-        self.image_data.width()
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // El archivo cambió en disco desde la última carga
+    pub fn is_modified(&self) -> bool {
+        let modified = modified_time(&self.path);
+        modified.is_some() && modified != self.modified.get()
+    }
+
+    // Vuelve a leer el archivo; si falla se conserva la imagen anterior
+    pub fn reload(&self) -> Result<(), ImageError> {
+        // La fecha se guarda aunque falle, para no reintentar un archivo roto en cada sondeo
+        self.modified.set(modified_time(&self.path));
+        let image_data = image::open(&self.path)?;
+        *self.buffer.borrow_mut() = TexelBuffer::from_image(&image_data)?;
+        Ok(())
     }
 
-    pub fn height(&self) -> u32 {
-        self.image_data.height()
+    // Bytes que ocupan los texeles en memoria
+    pub fn memory_bytes(&self) -> usize {
+        self.buffer.borrow().texels.len() * 3
     }
 
-    pub fn get_pixel(&self, x: u32, y: u32) -> [u8; 3] {
-        let pixel = self.image_data.get_pixel(x, y);
-        [pixel[0], pixel[1], pixel[2]]  // Retorna solo los valores RGB
+    // Muestreo al texel más cercano con coordenadas UV en [0, 1]
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        let buffer = self.buffer.borrow();
+        let u_clamped = u.clamp(0.0, 1.0 - f32::EPSILON);
+        let v_clamped = v.clamp(0.0, 1.0 - f32::EPSILON);
+
+        let tex_x = ((u_clamped * buffer.width as f32) as usize).min(buffer.width - 1);
+        let tex_y = ((v_clamped * buffer.height as f32) as usize).min(buffer.height - 1);
+
+        let texel = buffer.texels[tex_y * buffer.width + tex_x];
+        Color::from_u8(texel[0], texel[1], texel[2])
+    }
+}
+
+impl Texture for ImageTexture {
+    fn evaluate(&self, uv: (f32, f32), _point: &Vec3) -> Color {
+        self.sample(uv.0, uv.1)
     }
 }
 
-pub fn load_texture(file_path: &str) -> Rc<Texture> {
-    Rc::new(Texture::new(file_path))
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::procedural::{Checker, TextureSpace};
+
+    // Devuelve las UV como color, como haría una imagen
+    #[derive(Debug)]
+    struct UvTexture;
+
+    impl Texture for UvTexture {
+        fn evaluate(&self, uv: (f32, f32), _point: &Vec3) -> Color {
+            Color::new(uv.0, uv.1, 0.0)
+        }
+    }
+
+    #[test]
+    fn solid_mapping_projects_images_on_the_facing_plane() {
+        // Cara vertical mirando a +X: la imagen tiene que variar en Z e Y, no quedar estirada en X
+        let mapping = TextureMapping::Solid { scale: 1.0 };
+        let coords = TextureCoords::new((0.0, 0.0), Vec3::new(0.3, 0.2, 0.7), Vec3::new(1.0, 0.0, 0.0), mapping);
+        let color = coords.sample(&UvTexture);
+
+        assert!((color.r - 0.7).abs() < 1e-5);
+        assert!((color.g - 0.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn solid_mapping_evaluates_world_textures_at_the_scaled_point() {
+        let checker = Checker::new(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0), 1.0, TextureSpace::World);
+        let mapping = TextureMapping::Solid { scale: 2.0 };
+        let coords = |x: f32| TextureCoords::new((0.0, 0.0), Vec3::new(x, 0.5, 0.5), Vec3::new(0.0, 1.0, 0.0), mapping);
+
+        assert_eq!(coords(0.25).sample(&checker).r, 1.0);
+        assert_eq!(coords(0.75).sample(&checker).r, 0.0);
+    }
 }
@@ -0,0 +1,256 @@
+use nalgebra_glm::{Mat3, Mat4, Vec3, Vec4};
+
+use crate::bounds::Aabb;
+use crate::ray::Ray;
+use crate::ray_intersect::{Intersect, Interval, RayIntersect, SurfaceSample};
+use crate::sampling::Rng;
+
+// Objeto con una matriz de modelo: el rayo se lleva al espacio del objeto y el resultado vuelve a mundo
+pub struct Transformed<T: RayIntersect> {
+    pub object: T,
+    transform: Mat4,
+    inverse: Mat4,
+    // Inversa transpuesta, para que las normales sigan perpendiculares con escalas no uniformes
+    normal_matrix: Mat3,
+    // Caja en mundo para descartar rayos sin transformarlos
+    world_bounds: Option<Aabb>,
+}
+
+impl<T: RayIntersect> Transformed<T> {
+    // None si la matriz no es invertible, por ejemplo con una escala cero
+    pub fn new(object: T, transform: Mat4) -> Option<Self> {
+        let inverse = transform.try_inverse()?;
+        let normal_matrix = inverse.fixed_view::<3, 3>(0, 0).transpose();
+        let world_bounds = object.bounds().map(|bounds| bounds.transform(&transform));
+
+        Some(Transformed {
+            object,
+            transform,
+            inverse,
+            normal_matrix,
+            world_bounds,
+        })
+    }
+
+    fn to_world_point(&self, point: &Vec3) -> Vec3 {
+        (self.transform * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    fn to_world_vector(&self, vector: &Vec3) -> Vec3 {
+        (self.transform * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+    }
+
+    fn to_world_normal(&self, normal: &Vec3) -> Vec3 {
+        (self.normal_matrix * normal).normalize()
+    }
+
+    fn to_local_point(&self, point: &Vec3) -> Vec3 {
+        (self.inverse * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    fn to_local_vector(&self, vector: &Vec3) -> Vec3 {
+        (self.inverse * Vec4::new(vector.x, vector.y, vector.z, 0.0)).xyz()
+    }
+
+    // Cuánto se agranda un pedacito de superficie con esta normal en mundo: |det M| / |Mᵀ n|,
+    // que equivale a |det M| · |M⁻ᵀ n_local|. Devuelve también la normal local
+    fn area_scale(&self, world_normal: &Vec3) -> (f32, Vec3) {
+        let linear = self.transform.fixed_view::<3, 3>(0, 0);
+        let local_normal = linear.transpose() * world_normal;
+        let length = local_normal.magnitude();
+        (linear.determinant().abs() / length, local_normal / length)
+    }
+
+    // Rayo en espacio objeto con la dirección normalizada y el largo que tenía antes de normalizar;
+    // tmin y tmax se escalan para seguir marcando los mismos puntos
+    fn to_local_ray(&self, ray: &Ray) -> Option<(Ray, f32)> {
+        if self.world_bounds.is_some_and(|bounds| !bounds.hit(ray)) {
+            return None;
+        }
+
+        let local_direction = self.to_local_vector(&ray.direction);
+        let direction_length = local_direction.magnitude();
+        if direction_length <= 0.0 {
+            return None;
+        }
+
+        let local = Ray::new(self.to_local_point(&ray.origin), local_direction / direction_length)
+            .with_range(ray.tmin * direction_length, ray.tmax * direction_length);
+        Some((local, direction_length))
+    }
+
+    fn to_world_intersect<'a>(&self, local: Intersect<'a>, ray: &Ray, direction_length: f32) -> Intersect<'a> {
+        // La distancia local se mide con la dirección normalizada en espacio objeto
+        let distance = local.distance / direction_length;
+        let tangent = self.to_world_vector(&local.tangent);
+        let bitangent = self.to_world_vector(&local.bitangent);
+
+        Intersect {
+            point: ray.at(distance),
+            normal: self.to_world_normal(&local.normal),
+            distance,
+            tangent: if tangent == Vec3::zeros() { tangent } else { tangent.normalize() },
+            bitangent: if bitangent == Vec3::zeros() { bitangent } else { bitangent.normalize() },
+            ..local
+        }
+    }
+}
+
+impl<T: RayIntersect> RayIntersect for Transformed<T> {
+    fn ray_intersect(&self, ray: &Ray) -> Option<Intersect<'_>> {
+        let (local_ray, scale) = self.to_local_ray(ray)?;
+        let local = self.object.ray_intersect(&local_ray)?;
+        Some(self.to_world_intersect(local, ray, scale))
+    }
+
+    fn ray_intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let Some((local_ray, scale)) = self.to_local_ray(ray) else {
+            return Vec::new();
+        };
+
+        self.object
+            .ray_intervals(&local_ray)
+            .into_iter()
+            .map(|interval| Interval {
+                enter: interval.enter / scale,
+                exit: interval.exit / scale,
+            })
+            .collect()
+    }
+
+    fn boundary_hit(&self, ray: &Ray, distance: f32) -> Option<Intersect<'_>> {
+        let (local_ray, scale) = self.to_local_ray(ray)?;
+        let local = self.object.boundary_hit(&local_ray, distance * scale)?;
+        Some(self.to_world_intersect(local, ray, scale))
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.object.is_emissive()
+    }
+
+    // Exacto para rotaciones y escalas uniformes; con escalas no uniformes es una aproximación.
+    // El muestreo no depende de este valor: la densidad de cada muestra se corrige con area_scale
+    fn surface_area(&self) -> f32 {
+        let scale = self.transform.fixed_view::<3, 3>(0, 0).determinant().abs().cbrt();
+        self.object.surface_area() * scale * scale
+    }
+
+    // El punto se elige en espacio objeto; con escalas no uniformes unas caras se estiran más que otras
+    // y la densidad en mundo se divide por lo que creció el área en ese punto
+    fn sample_surface(&self, rng: &mut Rng) -> Option<SurfaceSample> {
+        let sample = self.object.sample_surface(rng)?;
+        let normal = self.to_world_normal(&sample.normal);
+        let (scale, _) = self.area_scale(&normal);
+        Some(SurfaceSample {
+            point: self.to_world_point(&sample.point),
+            normal,
+            pdf: sample.pdf / scale,
+            ..sample
+        })
+    }
+
+    fn surface_pdf(&self, point: &Vec3, normal: &Vec3) -> f32 {
+        let (scale, local_normal) = self.area_scale(normal);
+        self.object.surface_pdf(&self.to_local_point(point), &local_normal) / scale
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        self.world_bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::material::MaterialBuilder;
+    use nalgebra_glm::{rotation, scaling};
+    use std::f32::consts::PI;
+
+    // Cubo unitario estirado al doble en x: las caras x miden 1 y las demás 2, en total 10
+    fn stretched_cube() -> Transformed<Cube> {
+        let cube = Cube {
+            min_corner: Vec3::new(-0.5, -0.5, -0.5),
+            max_corner: Vec3::new(0.5, 0.5, 0.5),
+            material: MaterialBuilder::emissive(crate::color::Color::new(1.0, 1.0, 1.0)).build(),
+        };
+        Transformed::new(cube, scaling(&Vec3::new(2.0, 1.0, 1.0))).expect("la escala es invertible")
+    }
+
+    fn unit_cube() -> Cube {
+        Cube {
+            min_corner: Vec3::new(-0.5, -0.5, -0.5),
+            max_corner: Vec3::new(0.5, 0.5, 0.5),
+            material: MaterialBuilder::new().build(),
+        }
+    }
+
+    fn to_local(transform: &Mat4, point: &Vec3) -> Vec3 {
+        (transform.try_inverse().unwrap() * Vec4::new(point.x, point.y, point.z, 1.0)).xyz()
+    }
+
+    #[test]
+    fn singular_matrix_is_rejected() {
+        assert!(Transformed::new(unit_cube(), scaling(&Vec3::new(1.0, 0.0, 1.0))).is_none());
+    }
+
+    #[test]
+    fn rotated_and_stretched_cube_hit() {
+        // La escala va después de la rotación, así la caja queda torcida en mundo y la normal
+        // no es simplemente la normal local pasada por la matriz
+        let transform = scaling(&Vec3::new(2.0, 1.0, 1.0)) * rotation(PI / 4.0, &Vec3::new(0.0, 1.0, 0.0));
+        let object = Transformed::new(unit_cube(), transform).expect("la matriz es invertible");
+        let ray = Ray::new(Vec3::new(0.3, 0.1, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = object.ray_intersect(&ray).expect("debería chocar con la caja");
+
+        // El punto está sobre el rayo y en espacio objeto cae en una cara del cubo
+        assert!((hit.point - ray.at(hit.distance)).magnitude() < 1e-5);
+        let local = to_local(&transform, &hit.point);
+        let axis = (0..3).max_by(|a, b| local[*a].abs().total_cmp(&local[*b].abs())).unwrap();
+        assert!((local[axis].abs() - 0.5).abs() < 1e-4, "{:?}", local);
+        assert!((0..3).all(|i| local[i].abs() <= 0.5 + 1e-4), "{:?}", local);
+
+        // Un poco antes del impacto el rayo todavía está afuera
+        let before = to_local(&transform, &ray.at(hit.distance - 1e-2));
+        assert!((0..3).any(|i| before[i].abs() > 0.5), "{:?}", before);
+
+        // La normal es unitaria, mira hacia el rayo y es perpendicular a las aristas de la cara en mundo
+        assert!((hit.normal.magnitude() - 1.0).abs() < 1e-5);
+        assert!(hit.normal.dot(&ray.direction) < 0.0);
+        let linear = transform.fixed_view::<3, 3>(0, 0);
+        for edge_axis in (0..3).filter(|i| *i != axis) {
+            let mut edge = Vec3::zeros();
+            edge[edge_axis] = 1.0;
+            let world_edge = (linear * edge).normalize();
+            assert!(hit.normal.dot(&world_edge).abs() < 1e-5, "{:?} · {:?}", hit.normal, world_edge);
+        }
+    }
+
+    #[test]
+    fn surface_pdf_follows_stretched_faces() {
+        let object = stretched_cube();
+        // Cada cara local se elige con probabilidad 1/6; la cara x no cambia de área y las otras se duplican
+        let x_face = object.surface_pdf(&Vec3::new(1.0, 0.1, 0.2), &Vec3::new(1.0, 0.0, 0.0));
+        let y_face = object.surface_pdf(&Vec3::new(0.3, 0.5, 0.2), &Vec3::new(0.0, 1.0, 0.0));
+        assert!((x_face - 1.0 / 6.0).abs() < 1e-5, "{}", x_face);
+        assert!((y_face - 1.0 / 12.0).abs() < 1e-5, "{}", y_face);
+        assert_eq!(object.surface_pdf(&Vec3::new(0.0, 0.0, 0.0), &Vec3::new(1.0, 0.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn sampled_pdf_matches_surface_pdf_and_integrates_to_one() {
+        let object = stretched_cube();
+        let mut rng = Rng::new(7);
+        let count = 20000;
+        let mut area_estimate = 0.0;
+        for _ in 0..count {
+            let sample = object.sample_surface(&mut rng).expect("el cubo se puede muestrear");
+            let pdf = object.surface_pdf(&sample.point, &sample.normal);
+            assert!((sample.pdf - pdf).abs() < 1e-4, "{} != {}", sample.pdf, pdf);
+            area_estimate += 1.0 / sample.pdf;
+        }
+        // E[1 / pdf] es el área real en mundo; la aproximación con la raíz cúbica daría 9.52
+        let area = area_estimate / count as f32;
+        assert!((area - 10.0).abs() < 0.1, "{}", area);
+    }
+}
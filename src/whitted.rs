@@ -0,0 +1,164 @@
+use nalgebra_glm::{reflect_vec, Vec3};
+use std::f32::consts::PI;
+
+use crate::ambient_occlusion::AmbientOcclusion;
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::ray_intersect::Intersect;
+use crate::integrator::Integrator;
+use crate::pbr::sample_ggx_reflection;
+use crate::sampling::Rng;
+use crate::scene::Scene;
+use crate::settings::RenderSettings;
+
+pub struct WhittedIntegrator {
+    pub max_depth: u32,
+    // Oclusión ambiental opcional que oscurece el término ambiental
+    pub ambient_occlusion: Option<AmbientOcclusion>,
+    // Rayos por reflejo en materiales rugosos; con 0 todos los reflejos son de espejo
+    pub glossy_samples: u32,
+    // Muestras sobre los objetos emisivos; con 0 solo brillan ellos mismos
+    pub emitter_samples: u32,
+}
+
+impl WhittedIntegrator {
+    pub fn new(max_depth: u32, settings: &RenderSettings) -> Self {
+        WhittedIntegrator {
+            max_depth,
+            ambient_occlusion: None,
+            glossy_samples: settings.glossy_samples,
+            emitter_samples: settings.emitter_samples,
+        }
+    }
+
+    pub fn with_ambient_occlusion(mut self, ambient_occlusion: AmbientOcclusion) -> Self {
+        self.ambient_occlusion = Some(ambient_occlusion);
+        self
+    }
+
+    fn cast_ray(&self, scene: &Scene, ray: &Ray, depth: u32, rng: &mut Rng) -> Color {
+        let environment = &scene.environment;
+
+        if depth > self.max_depth {
+            return environment.background(ray);
+        }
+
+        let Some(intersect) = scene.intersect(ray) else {
+            return environment.background(ray);
+        };
+        let material = intersect.material;
+        let diffuse_color = intersect.diffuse();
+
+        let mut final_color = Color::black();
+
+        final_color = final_color + intersect.emission().unwrap_or(Color::black());
+
+        // Iluminación ambiental difusa y reflejo brillante del mapa de entorno
+        let view_dir = (ray.origin - intersect.point).normalize();
+        let reflect_dir = reflect_vec(&ray.direction, &intersect.normal).normalize();
+        let mut ambient = diffuse_color * environment.irradiance(&intersect.normal) * material.diffuse_weight;
+        if let Some(ambient_occlusion) = &self.ambient_occlusion {
+            ambient = ambient * ambient_occlusion.visibility(scene, &intersect, rng);
+        }
+        let fresnel = fresnel_schlick(intersect.normal.dot(&view_dir), 0.04);
+        let glossy = environment.specular(&reflect_dir, specular_to_roughness(material.specular))
+            * (material.specular_weight * fresnel);
+        final_color = final_color + ambient + glossy + self.sample_emitters(scene, &intersect, diffuse_color, rng);
+
+        // El reflejo no depende de las luces: se suma una sola vez
+        let reflectivity = material.reflectivity;
+        if reflectivity > 0.0 {
+            let reflect_color = self.trace_reflection(scene, &intersect, &view_dir, &reflect_dir, depth, rng);
+            final_color = final_color + reflect_color * reflectivity;
+        }
+
+        // Procesar cada luz en la escena, si hay alguna
+        for light in &scene.lights {
+            let light_dir = (light.position - intersect.point).normalize();
+
+            let diffuse_intensity = intersect.normal.dot(&light_dir).clamp(0.0, 1.0) * intersect.parallax_shadow(&light_dir);
+            let diffuse = diffuse_color * material.diffuse_weight * diffuse_intensity * light.intensity;
+
+            final_color = final_color + diffuse;
+        }
+
+        final_color
+    }
+
+    // Luz difusa que llega desde puntos muestreados sobre los objetos emisivos
+    fn sample_emitters(&self, scene: &Scene, intersect: &Intersect, diffuse_color: Color, rng: &mut Rng) -> Color {
+        if self.emitter_samples == 0 || !scene.has_emitters() || intersect.material.diffuse_weight <= 0.0 {
+            return Color::black();
+        }
+
+        let mut sum = Color::black();
+        for _ in 0..self.emitter_samples {
+            let Some(sample) = scene.sample_emitter(intersect, rng) else {
+                continue;
+            };
+            let cos_theta = intersect.normal.dot(&sample.direction);
+            if cos_theta <= 0.0 || scene.is_occluded(intersect, &sample.direction, sample.distance) {
+                continue;
+            }
+            sum = sum + sample.emission * (cos_theta / sample.pdf);
+        }
+
+        // BRDF de Lambert: albedo / PI
+        diffuse_color * sum * (intersect.material.diffuse_weight / (PI * self.emitter_samples as f32))
+    }
+
+    // Reflejo de espejo, o promedio de direcciones del lóbulo GGX si el material es rugoso.
+    // Solo el primer rebote reparte varias muestras; más abajo se traza una, así el costo no crece
+    // exponencialmente con la profundidad y el acumulador promedia el ruido entre cuadros
+    fn trace_reflection(
+        &self,
+        scene: &Scene,
+        intersect: &Intersect,
+        view_dir: &Vec3,
+        mirror_dir: &Vec3,
+        depth: u32,
+        rng: &mut Rng,
+    ) -> Color {
+        let roughness = intersect.material.roughness;
+        if roughness <= 0.0 || self.glossy_samples == 0 {
+            return self.cast_ray(scene, &intersect.spawn_ray(mirror_dir), depth + 1, rng);
+        }
+
+        let samples = if depth == 0 { self.glossy_samples } else { 1 };
+        let mut sum = Color::black();
+        for _ in 0..samples {
+            let mut direction = sample_ggx_reflection(&intersect.normal, view_dir, roughness, rng);
+            // Las direcciones que quedan bajo la superficie se cambian por el espejo
+            if direction.dot(&intersect.normal) <= 0.0 {
+                direction = *mirror_dir;
+            }
+            sum = sum + self.cast_ray(scene, &intersect.spawn_ray(&direction), depth + 1, rng);
+        }
+
+        sum * (1.0 / samples as f32)
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn name(&self) -> &str {
+        "Whitted"
+    }
+
+    // Con oclusión ambiental, reflejos rugosos o luces de área el resultado es ruidoso y conviene promediarlo
+    fn is_progressive(&self) -> bool {
+        self.ambient_occlusion.is_some() || self.glossy_samples > 0 || self.emitter_samples > 0
+    }
+
+    fn radiance(&self, scene: &Scene, ray: &Ray, rng: &mut Rng) -> Color {
+        self.cast_ray(scene, ray, 0, rng)
+    }
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+// Convierte el exponente especular de Phong en una rugosidad aproximada
+fn specular_to_roughness(specular: f32) -> f32 {
+    (2.0 / (specular.max(0.0) + 2.0)).sqrt()
+}